@@ -0,0 +1,963 @@
+use std::path::{Path, PathBuf};
+
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use serde::{
+    de::{value::MapAccessDeserializer, MapAccess, Visitor},
+    Deserialize,
+};
+use syn::{parse, LitInt, LitStr};
+
+use crate::{
+    expr::parse_int,
+    hjson::{self, OrderedMap},
+    names::{camel_case, is_fatal_alert},
+    registers,
+};
+
+/// Instance the lib prints through, stdout owns its handle
+const STDOUT_INSTANCE: &str = "uart0";
+
+/// Generates the `addresses` module of the `addresses!` macro with the arguments `args`
+///
+/// The path of the top description is relative to the OpenTitan repository at `opentitan_path`.
+/// Every register interface & memory of the modules gets an `opentitan_lib::devices::Address`
+/// constant, the instances of IPs with several instances are also grouped in arrays.
+pub fn addresses(
+    opentitan_path: &Path,
+    args: proc_macro2::TokenStream,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let top_path = syn::parse2::<LitStr>(args)?;
+    let span = top_path.span();
+    let (top, interfaces) = top_interfaces(opentitan_path, &top_path)?;
+    let groups = group_instances(&interfaces);
+
+    let names = interfaces.iter().map(|i| format_ident!("{}", i.name));
+    let hex = |value: usize| LitInt::new(&format!("{:#x}", value), span);
+    let bases = interfaces.iter().map(|i| hex(i.base));
+    let sizes = interfaces.iter().map(|i| hex(i.size));
+    let docs = interfaces.iter().map(|i| {
+        format!(
+            "`{}` {} at {:#x} ({:#x} bytes)",
+            i.module, i.kind, i.base, i.size
+        )
+    });
+
+    let module_names = interfaces.iter().map(|i| &i.module);
+    let module_types = interfaces.iter().map(|i| &i.ty);
+    let module_consts = interfaces.iter().map(|i| format_ident!("{}", i.name));
+    let module_count = interfaces.len();
+
+    let group_names = groups.iter().map(|g| format_ident!("{}", g.name));
+    let group_lens = groups.iter().map(|g| g.instances.len());
+    let group_instances = groups.iter().map(|g| {
+        g.instances
+            .iter()
+            .map(|name| format_ident!("{}", name))
+            .collect::<Vec<_>>()
+    });
+    let group_docs = groups.iter().map(|g| {
+        format!(
+            "Instances of `{}`: {}",
+            g.ty,
+            g.modules
+                .iter()
+                .map(|module| format!("`{}`", module))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    });
+
+    let plic_irq = gen_ids(
+        span,
+        "PlicIrq",
+        "Interrupt sources of the PLIC, ID 0 is reserved for \"no interrupt\"",
+        1,
+        top.interrupt_list.as_deref().unwrap_or_default(),
+    )?;
+    let alert_id = gen_ids(
+        span,
+        "AlertId",
+        "Alerts of the alert handler",
+        0,
+        top.alert_list.as_deref().unwrap_or_default(),
+    )?;
+    let alert_tests = gen_alert_tests(
+        span,
+        opentitan_path,
+        &top_dir(&top_path.value()),
+        &top,
+        &interfaces,
+    )?;
+    let peripherals = gen_peripherals(&top, &interfaces);
+
+    Ok(quote!(
+        #plic_irq
+        #alert_id
+        #alert_tests
+        #peripherals
+
+        pub mod addresses {
+            use opentitan_lib::devices::Address;
+
+            #(
+                #[doc = #docs]
+                pub const #names: Address = Address::new(#bases, #sizes);
+            )*
+            #(
+                #[doc = #group_docs]
+                pub const #group_names: [Address; #group_lens] = [#(#group_instances),*];
+            )*
+
+            /// Name, type & address range of every interface, eg. to find the module of an address
+            pub const MODULES: [(&str, &str, Address); #module_count] = [
+                #( (#module_names, #module_types, #module_consts) ),*
+            ];
+        }
+    ))
+}
+
+/// Lists the types of the IPs instantiated by the top described at `top_path`, eg. `aes`
+///
+/// The path of the top description is relative to the OpenTitan repository at `opentitan_path`.
+/// The types are sorted & every type is listed once.
+pub fn ip_types(opentitan_path: &Path, top_path: &str) -> Result<Vec<String>, String> {
+    let top = hjson::from_file::<TopDescription>(&opentitan_path.join(top_path))?;
+    Ok(module_types(top))
+}
+
+fn module_types(top: TopDescription) -> Vec<String> {
+    let mut types = top
+        .module
+        .into_iter()
+        .map(|module| module.ty)
+        .collect::<Vec<_>>();
+    types.sort();
+    types.dedup();
+    types
+}
+
+/// Reads the top description at `top_path` & lists the interfaces of its modules & memories
+///
+/// The sizes of the register interfaces are computed from the descriptions of the IPs.
+pub(crate) fn top_interfaces(
+    opentitan_path: &Path,
+    top_path: &LitStr,
+) -> syn::Result<(TopDescription, Vec<Interface>)> {
+    let path = opentitan_path.join(top_path.value());
+
+    // Read & Parse .hjson file of Top description
+    let top = hjson::from_file::<TopDescription>(&path)
+        .map_err(|error| parse::Error::new(top_path.span(), error))?;
+
+    let span = top_path.span();
+    let top_dir = top_dir(&top_path.value());
+    let interfaces = collect_interfaces(span, &top, |module, interface| {
+        let ip_path = ip_path(span, opentitan_path, &top_dir, module)?;
+        registers::interface_size(span, opentitan_path, &ip_path, interface)?.ok_or_else(|| {
+            parse::Error::new(
+                span,
+                format!(
+                    "{}: module {} uses the interface {}, which {} does not have",
+                    top_path.value(),
+                    module.name,
+                    interface,
+                    ip_path.display()
+                ),
+            )
+        })
+    })?;
+    Ok((top, interfaces))
+}
+
+/// Address range of a register interface or memory of a module
+#[derive(Debug, PartialEq)]
+pub(crate) struct Interface {
+    /// Name of the constant
+    pub(crate) name: String,
+    pub(crate) module: String,
+    pub(crate) ty: String,
+    /// Name of the interface in `base_addrs`, `None` for a lone `base_addr`
+    pub(crate) interface: Option<String>,
+    /// Description for the docs, eg. "registers" or "memory `mem`"
+    pub(crate) kind: String,
+    pub(crate) memory: bool,
+    pub(crate) base: usize,
+    pub(crate) size: usize,
+}
+
+/// Array constant of the same interface of all instances of an IP
+#[derive(Debug, PartialEq)]
+struct Group {
+    name: String,
+    ty: String,
+    modules: Vec<String>,
+    /// Names of the constants of the instances
+    instances: Vec<String>,
+}
+
+/// Lists the interfaces of all modules & memories of `top`
+///
+/// `register_size` returns the size of the register interface of a module, memories use their
+/// declared size.
+fn collect_interfaces(
+    span: Span,
+    top: &TopDescription,
+    mut register_size: impl FnMut(&ModuleDescription, &str) -> syn::Result<usize>,
+) -> syn::Result<Vec<Interface>> {
+    let error = |message: String| parse::Error::new(span, message);
+    let parse_addr = |s: &str, what: &str| {
+        parse_int(s)
+            .and_then(|value| usize::try_from(value).ok())
+            .ok_or_else(|| error(format!("invalid {} \"{}\"", what, s)))
+    };
+
+    let mut interfaces: Vec<Interface> = vec![];
+    for module in &top.module {
+        let bases = match (&module.base_addr, &module.base_addrs) {
+            (_, Some(OrderedMap(bases))) => bases
+                .iter()
+                .map(|(interface, base)| (Some(interface.clone()), base.0.clone()))
+                .collect(),
+            (Some(base), None) => vec![(None, base.clone())],
+            (None, None) => {
+                return Err(error(format!(
+                    "module {} has neither base_addr nor base_addrs",
+                    module.name
+                )))
+            }
+        };
+        let single = bases.len() == 1;
+
+        for (interface, base) in bases {
+            let base = parse_addr(&base, &format!("base address of module {}", module.name))?;
+            let memory = interface.as_ref().and_then(|interface| {
+                module.memory.as_ref().and_then(|OrderedMap(memories)| {
+                    memories.iter().find(|(name, _)| name == interface)
+                })
+            });
+            let (kind, size) = match memory {
+                Some((name, memory)) => (
+                    format!("memory `{}`", name),
+                    parse_addr(&memory.size, &format!("size of memory {}", name))?,
+                ),
+                None => {
+                    let size = register_size(module, interface.as_deref().unwrap_or("null"))?;
+                    let kind = match &interface {
+                        Some(interface) if !single && interface != "null" => {
+                            format!("registers `{}`", interface)
+                        }
+                        _ => "registers".to_owned(),
+                    };
+                    // Register blocks occupy the next power of two, like reggen assigns them
+                    (kind, size.next_power_of_two().max(4))
+                }
+            };
+
+            let name = match &interface {
+                Some(interface) if !single && interface != "null" => {
+                    format!("{}_{}", module.name, interface)
+                }
+                _ => module.name.clone(),
+            };
+            interfaces.push(Interface {
+                name: name.to_uppercase(),
+                module: module.name.clone(),
+                ty: module.ty.clone(),
+                interface,
+                kind,
+                memory: memory.is_some(),
+                base,
+                size,
+            });
+        }
+    }
+
+    for memory in top.memory.iter().flatten() {
+        interfaces.push(Interface {
+            name: memory.name.to_uppercase(),
+            module: memory.name.clone(),
+            ty: memory.name.clone(),
+            interface: None,
+            kind: "memory".to_owned(),
+            memory: true,
+            base: parse_addr(
+                &memory.base_addr,
+                &format!("base address of memory {}", memory.name),
+            )?,
+            size: parse_addr(&memory.size, &format!("size of memory {}", memory.name))?,
+        });
+    }
+
+    for (index, interface) in interfaces.iter().enumerate() {
+        if interfaces[..index].iter().any(|i| i.name == interface.name) {
+            return Err(error(format!(
+                "the address constant {} of {} is defined twice",
+                interface.name, interface.module
+            )));
+        }
+    }
+    Ok(interfaces)
+}
+
+/// Groups the same interface of all instances of IPs with several instances
+fn group_instances(interfaces: &[Interface]) -> Vec<Group> {
+    let mut groups: Vec<(&str, &Option<String>, Vec<&Interface>)> = vec![];
+    for interface in interfaces {
+        match groups
+            .iter_mut()
+            .find(|(ty, name, _)| *ty == interface.ty && **name == interface.interface)
+        {
+            Some((_, _, instances)) => instances.push(interface),
+            None => groups.push((&interface.ty, &interface.interface, vec![interface])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter(|(_, _, instances)| instances.len() > 1)
+        .map(|(ty, interface, instances)| {
+            // Single interface instances use the interface name only if the type has several
+            let multiple = interfaces
+                .iter()
+                .any(|i| i.ty == ty && i.interface != *interface);
+            let mut name = match interface {
+                Some(interface) if multiple && interface != "null" => {
+                    format!("{}_{}", ty, interface).to_uppercase()
+                }
+                _ => ty.to_uppercase(),
+            };
+            if interfaces.iter().any(|i| i.name == name) {
+                name.push_str("_INSTANCES");
+            }
+            Group {
+                name,
+                ty: ty.to_owned(),
+                modules: instances.iter().map(|i| i.module.clone()).collect(),
+                instances: instances.iter().map(|i| i.name.clone()).collect(),
+            }
+        })
+        .collect()
+}
+
+/// Signal of a top with its number, signals wider than one bit have an id per bit
+pub(crate) struct SignalId {
+    /// Name of the signal, followed by the bit for wide signals, eg. `gpio_gpio_0`
+    pub(crate) name: String,
+    pub(crate) module: String,
+    pub(crate) bit: Option<usize>,
+    pub(crate) desc: String,
+    pub(crate) id: u32,
+}
+
+/// Numbers the signals `signals` of a top, starting at `first_id`
+pub(crate) fn signal_ids(
+    span: Span,
+    first_id: u32,
+    signals: &[SignalDescription],
+) -> syn::Result<Vec<SignalId>> {
+    let mut ids = vec![];
+    for signal in signals {
+        let width = match &signal.width {
+            Some(width) => parse_int(width).filter(|width| *width > 0).ok_or_else(|| {
+                parse::Error::new(
+                    span,
+                    format!("invalid width \"{}\" of signal {}", width, signal.name),
+                )
+            })?,
+            None => 1,
+        };
+        for bit in 0..width as usize {
+            let (name, bit) = if width > 1 {
+                (format!("{}_{}", signal.name, bit), Some(bit))
+            } else {
+                (signal.name.clone(), None)
+            };
+            ids.push(SignalId {
+                name,
+                module: signal
+                    .module_name
+                    .clone()
+                    .unwrap_or_else(|| "top".to_owned()),
+                bit,
+                desc: signal.desc.clone().unwrap_or_else(|| signal.name.clone()),
+                id: first_id + ids.len() as u32,
+            });
+        }
+    }
+    Ok(ids)
+}
+
+/// Generates the enum `name` of the numbered signals `signals` of a top, starting at `first_id`
+///
+/// Signals wider than one bit get a variant per bit, like the generated C headers. Nothing is
+/// generated if the top has no such signals.
+fn gen_ids(
+    span: Span,
+    name: &str,
+    doc: &str,
+    first_id: u32,
+    signals: &[SignalDescription],
+) -> syn::Result<proc_macro2::TokenStream> {
+    let signals = signal_ids(span, first_id, signals)?;
+    let variants = signals
+        .iter()
+        .map(|signal| format_ident!("{}", camel_case(&signal.name)))
+        .collect::<Vec<_>>();
+    let docs = signals.iter().map(|signal| match signal.bit {
+        Some(bit) => format!("(`{}` bit {}) {}", signal.module, bit, signal.desc),
+        None => format!("(`{}`) {}", signal.module, signal.desc),
+    });
+    if variants.is_empty() {
+        return Ok(quote!());
+    }
+
+    let name = format_ident!("{}", name);
+    let ids = signals.iter().map(|signal| signal.id).collect::<Vec<_>>();
+    Ok(quote!(
+        #[doc = #doc]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #[repr(u32)]
+        pub enum #name {
+            #(
+                #[doc = #docs]
+                #variants = #ids,
+            )*
+        }
+
+        impl From<#name> for u32 {
+            fn from(id: #name) -> u32 {
+                id as u32
+            }
+        }
+
+        impl TryFrom<u32> for #name {
+            type Error = opentitan_lib::Error;
+
+            fn try_from(id: u32) -> Result<Self, opentitan_lib::Error> {
+                match id {
+                    #(#ids => Ok(#name::#variants),)*
+                    _ => Err(opentitan_lib::Error::InvalidArgument),
+                }
+            }
+        }
+    ))
+}
+
+/// Generates the module, the alert test register & the class of every `AlertId`
+///
+/// Alerts are raised through the interface of their module with the alert test register, their
+/// bits are numbered in the order the top lists the alerts of the module.
+fn gen_alert_tests(
+    span: Span,
+    opentitan_path: &Path,
+    top_dir: &Path,
+    top: &TopDescription,
+    interfaces: &[Interface],
+) -> syn::Result<proc_macro2::TokenStream> {
+    let alerts = signal_ids(span, 0, top.alert_list.as_deref().unwrap_or_default())?;
+    if alerts.is_empty() {
+        return Ok(quote!());
+    }
+    let error = |message: String| parse::Error::new(span, message);
+
+    // Address of the alert test register of every module with alerts
+    let mut test_registers: Vec<(&str, usize)> = vec![];
+    let mut registers = vec![];
+    for (index, alert) in alerts.iter().enumerate() {
+        let address = match test_registers
+            .iter()
+            .find(|(module, _)| *module == alert.module)
+        {
+            Some((_, address)) => *address,
+            None => {
+                let module = top
+                    .module
+                    .iter()
+                    .find(|module| module.name == alert.module)
+                    .ok_or_else(|| {
+                        error(format!(
+                            "alert {} of the unknown module {}",
+                            alert.name, alert.module
+                        ))
+                    })?;
+                let ip_path = ip_path(span, opentitan_path, top_dir, module)?;
+                let (name, offset) =
+                    registers::alert_test_register(span, opentitan_path, &ip_path)?;
+                let interface = interfaces
+                    .iter()
+                    .find(|interface| {
+                        interface.module == module.name
+                            && !interface.memory
+                            && (name.is_none() || interface.interface == name)
+                    })
+                    .ok_or_else(|| {
+                        error(format!(
+                            "module {} has alerts but no interface with the alert test register",
+                            module.name
+                        ))
+                    })?;
+                test_registers.push((&alert.module, interface.base + offset));
+                interface.base + offset
+            }
+        };
+        let bit = alerts[..index]
+            .iter()
+            .filter(|other| other.module == alert.module)
+            .count();
+        registers.push((address, bit));
+    }
+
+    let variants = alerts
+        .iter()
+        .map(|alert| format_ident!("{}", camel_case(&alert.name)))
+        .collect::<Vec<_>>();
+    let count = alerts.len();
+    let modules = alerts.iter().map(|alert| &alert.module);
+    let addresses = registers
+        .iter()
+        .map(|(address, _)| LitInt::new(&format!("{:#x}", address), span));
+    let masks = registers
+        .iter()
+        .map(|(_, bit)| LitInt::new(&format!("{:#x}", 1u32 << bit), span));
+    let classes = alerts.iter().map(|alert| {
+        let name = alert
+            .name
+            .strip_prefix(&format!("{}_", alert.module))
+            .unwrap_or(&alert.name);
+        if is_fatal_alert(name) {
+            quote!(Fatal)
+        } else {
+            quote!(Recoverable)
+        }
+    });
+
+    Ok(quote!(
+        impl AlertId {
+            /// All alerts of the top, in the order of their ids
+            pub const ALL: [AlertId; #count] = [#(AlertId::#variants),*];
+
+            /// Returns the name of the module raising the alert
+            pub const fn module(self) -> &'static str {
+                match self {
+                    #(AlertId::#variants => #modules,)*
+                }
+            }
+
+            /// Returns the address of the alert test register of the module & the mask of the
+            /// bit raising the alert
+            pub const fn test_register(self) -> (usize, u32) {
+                match self {
+                    #(AlertId::#variants => (#addresses, #masks),)*
+                }
+            }
+
+            /// Returns whether the alert is fatal or recoverable, taken from its name
+            pub const fn class(self) -> opentitan_lib::devices::alerts::AlertClass {
+                match self {
+                    #(AlertId::#variants => opentitan_lib::devices::alerts::AlertClass::#classes,)*
+                }
+            }
+        }
+    ))
+}
+
+/// Generates the `peripherals` module with a handle per module of `top` & the `Peripherals` struct
+///
+/// The handles refer to the first register interface of their module, or to its first memory if it
+/// has no registers. Every IP type gets an uninhabited marker in `peripherals::ip`, so drivers can
+/// accept the handles of all instances of their IP. The handle of the stdout instance is optional,
+/// `Peripherals::take` only hands it out if stdout does not own it.
+fn gen_peripherals(top: &TopDescription, interfaces: &[Interface]) -> proc_macro2::TokenStream {
+    let mut types = top
+        .module
+        .iter()
+        .map(|module| module.ty.as_str())
+        .collect::<Vec<_>>();
+    types.sort();
+    types.dedup();
+    let type_docs = types.iter().map(|ty| format!("Instances of `{}`", ty));
+    let types = types.iter().map(|ty| format_ident!("{}", camel_case(ty)));
+
+    let modules = top
+        .module
+        .iter()
+        .filter_map(|module| {
+            let mut module_interfaces = interfaces.iter().filter(|i| i.module == module.name);
+            let interface = module_interfaces
+                .clone()
+                .find(|i| !i.memory)
+                .or_else(|| module_interfaces.next())?;
+            Some((module, interface))
+        })
+        .collect::<Vec<_>>();
+    let fields = modules
+        .iter()
+        .map(|(module, _)| format_ident!("{}", module.name))
+        .collect::<Vec<_>>();
+    let handles = modules
+        .iter()
+        .map(|(module, _)| format_ident!("{}", camel_case(&module.name)))
+        .collect::<Vec<_>>();
+    let handle_docs = modules
+        .iter()
+        .map(|(module, _)| format!("Handle of `{}`", module.name));
+    let (field_types, field_values): (Vec<_>, Vec<_>) = modules
+        .iter()
+        .zip(&handles)
+        .map(|((module, _), handle)| {
+            if module.name == STDOUT_INSTANCE {
+                (
+                    quote!(Option<peripherals::#handle>),
+                    quote!(Some(peripherals::#handle::steal())),
+                )
+            } else {
+                (
+                    quote!(peripherals::#handle),
+                    quote!(peripherals::#handle::steal()),
+                )
+            }
+        })
+        .unzip();
+    let names = modules.iter().map(|(module, _)| &module.name);
+    let ips = modules
+        .iter()
+        .map(|(module, _)| format_ident!("{}", camel_case(&module.ty)));
+    let addresses = modules
+        .iter()
+        .map(|(_, interface)| format_ident!("{}", interface.name));
+
+    quote!(
+        /// Owned handles of the IP instances, handed out once by `Peripherals::take`
+        pub mod peripherals {
+            /// Types of the IPs, drivers accept the handles of the instances of their IP
+            pub mod ip {
+                #(
+                    #[doc = #type_docs]
+                    pub enum #types {}
+                )*
+            }
+
+            #(
+                #[doc = #handle_docs]
+                #[derive(Debug)]
+                pub struct #handles {
+                    _private: (),
+                }
+
+                impl #handles {
+                    /// Creates a handle of the instance, regardless of the one of `Peripherals`
+                    ///
+                    /// # Safety
+                    /// The instance must not be used through another handle at the same time.
+                    pub const unsafe fn steal() -> Self {
+                        #handles { _private: () }
+                    }
+                }
+
+                unsafe impl opentitan_lib::devices::Peripheral for #handles {
+                    type Ip = ip::#ips;
+                    const NAME: &'static str = #names;
+                    const ADDRESS: opentitan_lib::devices::Address = super::addresses::#addresses;
+                }
+            )*
+        }
+
+        /// Handles of all IP instances of the top, see `Peripherals::take`
+        #[derive(Debug)]
+        pub struct Peripherals {
+            #(pub #fields: #field_types,)*
+        }
+
+        impl Peripherals {
+            /// Creates the handles of all instances, regardless of the ones handed out before
+            ///
+            /// # Safety
+            /// The instances must not be used through other handles at the same time.
+            pub unsafe fn steal() -> Self {
+                Peripherals {
+                    #(#fields: #field_values,)*
+                }
+            }
+        }
+    )
+}
+
+/// Returns the directory of the top described at `top_path`, eg. `hw/top_earlgrey`
+pub(crate) fn top_dir(top_path: &str) -> PathBuf {
+    Path::new(top_path)
+        .parent()
+        .and_then(Path::parent)
+        .map(Path::to_owned)
+        .unwrap_or_default()
+}
+
+/// Finds the description of the IP of `module`
+///
+/// Templated IPs are generated for the top, so its directory is searched before `hw/ip`.
+pub(crate) fn ip_path(
+    span: Span,
+    opentitan_path: &Path,
+    top_dir: &Path,
+    module: &ModuleDescription,
+) -> syn::Result<PathBuf> {
+    let file = format!("{}.hjson", module.ty);
+    let candidates = [
+        top_dir
+            .join("ip_autogen")
+            .join(&module.ty)
+            .join("data")
+            .join(&file),
+        top_dir
+            .join("ip")
+            .join(&module.ty)
+            .join("data")
+            .join("autogen")
+            .join(&file),
+        top_dir.join("ip").join(&module.ty).join("data").join(&file),
+        Path::new("hw/ip").join(&module.ty).join("data").join(&file),
+    ];
+    candidates
+        .iter()
+        .find(|path| opentitan_path.join(path).is_file())
+        .cloned()
+        .ok_or_else(|| {
+            let tried = candidates
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            parse::Error::new(
+                span,
+                format!(
+                    "no description of the IP {} of module {} found, tried {}",
+                    module.ty, module.name, tried
+                ),
+            )
+        })
+}
+
+#[derive(Deserialize)]
+pub(crate) struct TopDescription {
+    pub(crate) module: Vec<ModuleDescription>,
+    pub(crate) memory: Option<Vec<MemoryDescription>>,
+    pub(crate) interrupt_list: Option<Vec<SignalDescription>>,
+    pub(crate) alert_list: Option<Vec<SignalDescription>>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ModuleDescription {
+    pub(crate) name: String,
+    #[serde(rename = "type")]
+    pub(crate) ty: String,
+    pub(crate) base_addr: Option<String>,
+    pub(crate) base_addrs: Option<OrderedMap<BaseAddress>>,
+    pub(crate) memory: Option<OrderedMap<ModuleMemory>>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ModuleMemory {
+    pub(crate) label: Option<String>,
+    pub(crate) exec: Option<String>,
+    pub(crate) size: String,
+}
+
+/// Interrupt or alert of a module, as listed by the top
+#[derive(Deserialize)]
+pub(crate) struct SignalDescription {
+    pub(crate) name: String,
+    pub(crate) width: Option<String>,
+    pub(crate) module_name: Option<String>,
+    pub(crate) desc: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct MemoryDescription {
+    pub(crate) name: String,
+    pub(crate) base_addr: String,
+    pub(crate) size: String,
+    pub(crate) exec: Option<String>,
+}
+
+/// Base address of an interface
+///
+/// Newer tops give the address per address space, eg. `{hart: "0x40000000"}`, the address seen
+/// by the hart is used.
+pub(crate) struct BaseAddress(pub(crate) String);
+
+impl<'de> Deserialize<'de> for BaseAddress {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BaseAddressVisitor;
+
+        impl<'de> Visitor<'de> for BaseAddressVisitor {
+            type Value = BaseAddress;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("an address or an object of addresses per address space")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Self::Value, E> {
+                Ok(BaseAddress(s.to_owned()))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                let OrderedMap(spaces) =
+                    OrderedMap::<String>::deserialize(MapAccessDeserializer::new(map))?;
+                let address = match spaces.iter().find(|(space, _)| space == "hart") {
+                    Some((_, address)) => address.clone(),
+                    None => spaces
+                        .into_iter()
+                        .next()
+                        .map(|(_, address)| address)
+                        .ok_or_else(|| serde::de::Error::custom("no address space"))?,
+                };
+                Ok(BaseAddress(address))
+            }
+        }
+
+        deserializer.deserialize_any(BaseAddressVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOP: &str = r#"{
+        module: [
+            { name: "uart0", type: "uart", base_addr: "0x40000000" },
+            { name: "uart1", type: "uart", base_addr: "0x40010000" },
+            { name: "flash_ctrl", type: "flash_ctrl",
+              base_addrs: { core: "0x41000000", prim: "0x41008000", mem: "0x20000000" },
+              memory: { mem: { label: "eflash", size: "0x100000" } } },
+            { name: "sram_ctrl_main", type: "sram_ctrl",
+              base_addrs: { regs: { hart: "0x411c0000" }, ram: { hart: "0x10000000" } },
+              memory: { ram: { size: "0x20000" } } },
+            { name: "sram_ctrl_ret", type: "sram_ctrl",
+              base_addrs: { regs: { hart: "0x40500000" }, ram: { hart: "0x40600000" } },
+              memory: { ram: { size: "0x1000" } } },
+            { name: "hmac", type: "hmac", base_addrs: { null: "0x41110000" } },
+        ],
+        memory: [{ name: "rom", base_addr: "0x8000", size: "0x8000" }],
+    }"#;
+
+    fn interfaces() -> Vec<Interface> {
+        let top = hjson::from_str::<TopDescription>(TOP).unwrap();
+        collect_interfaces(Span::call_site(), &top, |module, _| {
+            Ok(if module.ty == "hmac" { 0x1000 } else { 0x30 })
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn interfaces_of_modules() {
+        let interfaces = interfaces();
+        let summary = interfaces
+            .iter()
+            .map(|i| (i.name.as_str(), i.base, i.size))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                ("UART0", 0x4000_0000, 0x40),
+                ("UART1", 0x4001_0000, 0x40),
+                ("FLASH_CTRL_CORE", 0x4100_0000, 0x40),
+                ("FLASH_CTRL_PRIM", 0x4100_8000, 0x40),
+                ("FLASH_CTRL_MEM", 0x2000_0000, 0x10_0000),
+                ("SRAM_CTRL_MAIN_REGS", 0x411c_0000, 0x40),
+                ("SRAM_CTRL_MAIN_RAM", 0x1000_0000, 0x2_0000),
+                ("SRAM_CTRL_RET_REGS", 0x4050_0000, 0x40),
+                ("SRAM_CTRL_RET_RAM", 0x4060_0000, 0x1000),
+                ("HMAC", 0x4111_0000, 0x1000),
+                ("ROM", 0x8000, 0x8000),
+            ]
+        );
+        assert_eq!(interfaces[4].kind, "memory `mem`");
+    }
+
+    #[test]
+    fn instance_groups() {
+        let groups = group_instances(&interfaces());
+        let summary = groups
+            .iter()
+            .map(|g| (g.name.as_str(), g.instances.join(" ")))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                ("UART", "UART0 UART1".to_owned()),
+                (
+                    "SRAM_CTRL_REGS",
+                    "SRAM_CTRL_MAIN_REGS SRAM_CTRL_RET_REGS".to_owned()
+                ),
+                (
+                    "SRAM_CTRL_RAM",
+                    "SRAM_CTRL_MAIN_RAM SRAM_CTRL_RET_RAM".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn peripherals() {
+        let top = hjson::from_str::<TopDescription>(TOP).unwrap();
+        let code = gen_peripherals(&top, &interfaces()).to_string();
+        assert!(code.contains("pub enum SramCtrl { }"));
+        assert!(code.contains("pub struct Uart1"));
+        assert!(code.contains("type Ip = ip :: Uart"));
+        // The registers of a module with a memory are its address
+        assert!(code.contains("super :: addresses :: FLASH_CTRL_CORE"));
+        assert!(code.contains("super :: addresses :: SRAM_CTRL_MAIN_REGS"));
+        assert!(code.contains("pub sram_ctrl_ret : peripherals :: SramCtrlRet"));
+        // Stdout may own the handle of uart0
+        assert!(code.contains("pub uart0 : Option < peripherals :: Uart0 >"));
+        assert!(code.contains("uart0 : Some (peripherals :: Uart0 :: steal ())"));
+        // Memories of the top are not IP instances
+        assert!(!code.contains("Rom"));
+    }
+
+    #[test]
+    fn types_of_modules() {
+        let top = hjson::from_str::<TopDescription>(TOP).unwrap();
+        assert_eq!(
+            module_types(top),
+            ["flash_ctrl", "hmac", "sram_ctrl", "uart"]
+        );
+    }
+
+    #[test]
+    fn signal_ids() {
+        let signals = hjson::from_str::<Vec<SignalDescription>>(
+            r#"[
+                { name: "gpio_gpio", width: 2, module_name: "gpio", desc: "pin" },
+                { name: "uart0_tx_watermark", width: "1", module_name: "uart0" },
+            ]"#,
+        )
+        .unwrap();
+        let ids = gen_ids(Span::call_site(), "PlicIrq", "Sources", 1, &signals).unwrap();
+        let ids = ids.to_string();
+        assert!(ids.contains("GpioGpio0 = 1u32"));
+        assert!(ids.contains("GpioGpio1 = 2u32"));
+        assert!(ids.contains("Uart0TxWatermark = 3u32"));
+        assert!(ids.contains("3u32 => Ok (PlicIrq :: Uart0TxWatermark)"));
+
+        assert!(gen_ids(Span::call_site(), "AlertId", "", 0, &[])
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn duplicate_constants() {
+        let top = hjson::from_str::<TopDescription>(
+            r#"{ module: [
+                { name: "rom", type: "rom_ctrl", base_addr: "0x1000" },
+            ], memory: [{ name: "rom", base_addr: "0x8000", size: "0x8000" }] }"#,
+        )
+        .unwrap();
+        let error = collect_interfaces(Span::call_site(), &top, |_, _| Ok(4)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the address constant ROM of rom is defined twice"
+        );
+    }
+}
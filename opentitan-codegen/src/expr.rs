@@ -0,0 +1,244 @@
+//! Evaluation of the simple integer expressions used by IP descriptions
+//!
+//! Counts, window sizes & parameter defaults may reference parameters and combine them using
+//! `+ - * / %`, shifts & parentheses, eg. `NumAlerts * 2 + 1`. Integers may be written in
+//! decimal, hexadecimal (`0x`), octal (`0o`), binary (`0b`) or SystemVerilog (`32'h1f`) notation.
+
+/// Evaluates the expression `s`, parameters are resolved with `param`
+///
+/// `param` returns `None` for unknown parameters.
+pub fn eval(
+    s: &str,
+    param: &mut dyn FnMut(&str) -> Option<Result<u64, String>>,
+) -> Result<u64, String> {
+    let tokens = tokenize(s)?;
+    let mut parser = Parser {
+        tokens: &tokens,
+        pos: 0,
+        param,
+    };
+    let value = parser.shift()?;
+    match parser.tokens.get(parser.pos) {
+        None => Ok(value),
+        Some(token) => Err(format!("unexpected {} in \"{}\"", token, s)),
+    }
+}
+
+/// Parses an integer literal, `None` if `s` is no valid literal
+pub fn parse_int(s: &str) -> Option<u64> {
+    let s = s.trim().replace('_', "");
+    // SystemVerilog literals have an optional width & a mandatory base
+    if let Some((width, literal)) = s.split_once('\'') {
+        if !width.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let literal = literal.strip_prefix(['s', 'S']).unwrap_or(literal);
+        let radix = match literal.chars().next()?.to_ascii_lowercase() {
+            'h' => 16,
+            'd' => 10,
+            'o' => 8,
+            'b' => 2,
+            _ => return None,
+        };
+        return u64::from_str_radix(&literal[1..], radix).ok();
+    }
+
+    let (digits, radix) = if let Some(hex) = s.strip_prefix("0x").or(s.strip_prefix("0X")) {
+        (hex, 16)
+    } else if let Some(oct) = s.strip_prefix("0o") {
+        (oct, 8)
+    } else if let Some(bin) = s.strip_prefix("0b").or(s.strip_prefix("0B")) {
+        (bin, 2)
+    } else {
+        (s.as_str(), 10)
+    };
+    u64::from_str_radix(digits, radix).ok()
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Int(u64),
+    Param(String),
+    Op(&'static str),
+    Open,
+    Close,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Int(value) => write!(f, "`{}`", value),
+            Token::Param(name) => write!(f, "`{}`", name),
+            Token::Op(op) => write!(f, "`{}`", op),
+            Token::Open => write!(f, "`(`"),
+            Token::Close => write!(f, "`)`"),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut rest = s.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let len = if c.is_ascii_alphanumeric() || c == '_' {
+            // Literals & parameter names, SystemVerilog literals contain a `'`
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '\''))
+                .unwrap_or(rest.len());
+            let word = &rest[..len];
+            if c.is_ascii_digit() {
+                let value =
+                    parse_int(word).ok_or_else(|| format!("invalid integer \"{}\"", word))?;
+                tokens.push(Token::Int(value));
+            } else {
+                tokens.push(Token::Param(word.to_owned()));
+            }
+            len
+        } else if c == '(' {
+            tokens.push(Token::Open);
+            1
+        } else if c == ')' {
+            tokens.push(Token::Close);
+            1
+        } else {
+            let op = ["<<", ">>", "+", "-", "*", "/", "%"]
+                .into_iter()
+                .find(|op| rest.starts_with(op))
+                .ok_or_else(|| format!("unexpected `{}` in \"{}\"", c, s))?;
+            tokens.push(Token::Op(op));
+            op.len()
+        };
+        rest = rest[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser evaluating the tokens while parsing them
+struct Parser<'a, 'p> {
+    tokens: &'a [Token],
+    pos: usize,
+    param: &'p mut dyn FnMut(&str) -> Option<Result<u64, String>>,
+}
+
+impl Parser<'_, '_> {
+    /// Parses a chain of binary operators of the same precedence
+    fn binary(
+        &mut self,
+        ops: &[&str],
+        operand: fn(&mut Self) -> Result<u64, String>,
+    ) -> Result<u64, String> {
+        let mut value = operand(self)?;
+        while let Some(Token::Op(op)) = self.tokens.get(self.pos) {
+            if !ops.contains(op) {
+                break;
+            }
+            self.pos += 1;
+            let rhs = operand(self)?;
+            value = match *op {
+                "<<" => u32::try_from(rhs)
+                    .ok()
+                    .and_then(|rhs| value.checked_shl(rhs)),
+                ">>" => u32::try_from(rhs)
+                    .ok()
+                    .and_then(|rhs| value.checked_shr(rhs)),
+                "+" => value.checked_add(rhs),
+                "-" => value.checked_sub(rhs),
+                "*" => value.checked_mul(rhs),
+                "/" => value.checked_div(rhs),
+                _ => value.checked_rem(rhs),
+            }
+            .ok_or_else(|| format!("`{} {} {}` is out of range", value, op, rhs))?;
+        }
+        Ok(value)
+    }
+
+    fn shift(&mut self) -> Result<u64, String> {
+        self.binary(&["<<", ">>"], Self::sum)
+    }
+
+    fn sum(&mut self) -> Result<u64, String> {
+        self.binary(&["+", "-"], Self::product)
+    }
+
+    fn product(&mut self) -> Result<u64, String> {
+        self.binary(&["*", "/", "%"], Self::primary)
+    }
+
+    fn primary(&mut self) -> Result<u64, String> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        match token {
+            Some(Token::Int(value)) => Ok(*value),
+            Some(Token::Param(name)) => {
+                (self.param)(name).unwrap_or_else(|| Err(format!("unknown parameter {}", name)))
+            }
+            Some(Token::Open) => {
+                let value = self.shift()?;
+                match self.tokens.get(self.pos) {
+                    Some(Token::Close) => {
+                        self.pos += 1;
+                        Ok(value)
+                    }
+                    _ => Err("missing `)`".to_owned()),
+                }
+            }
+            Some(token) => Err(format!("unexpected {}", token)),
+            None => Err("unexpected end of expression".to_owned()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval_with(s: &str, params: &[(&str, u64)]) -> Result<u64, String> {
+        eval(s, &mut |name| {
+            params
+                .iter()
+                .find(|(param, _)| *param == name)
+                .map(|(_, value)| Ok(*value))
+        })
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(parse_int("42"), Some(42));
+        assert_eq!(parse_int("0x1_0"), Some(16));
+        assert_eq!(parse_int("0b101"), Some(5));
+        assert_eq!(parse_int("32'hdead"), Some(0xdead));
+        assert_eq!(parse_int("'d7"), Some(7));
+        assert_eq!(parse_int("8'sb11"), Some(3));
+        assert_eq!(parse_int("NumSrc"), None);
+    }
+
+    #[test]
+    fn expressions() {
+        let params = [("NumSrc", 185), ("NumTargets", 2)];
+        assert_eq!(eval_with("NumSrc", &params), Ok(185));
+        assert_eq!(eval_with("1 + 2 * 3", &params), Ok(7));
+        assert_eq!(eval_with("(NumSrc + 31) / 32", &params), Ok(6));
+        assert_eq!(eval_with("NumTargets << 2 + 1", &params), Ok(16));
+        assert_eq!(eval_with("NumSrc % 32", &params), Ok(25));
+        assert_eq!(eval_with("0x10 - 1", &params), Ok(15));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            eval_with("NumFoo + 1", &[]),
+            Err("unknown parameter NumFoo".to_owned())
+        );
+        assert_eq!(
+            eval_with("1 - 2", &[]),
+            Err("`1 - 2` is out of range".to_owned())
+        );
+        assert_eq!(eval_with("(1 + 2", &[]), Err("missing `)`".to_owned()));
+        assert_eq!(
+            eval_with("1 2", &[]),
+            Err("unexpected `2` in \"1 2\"".to_owned())
+        );
+        assert!(eval_with("4 / 0", &[]).is_err());
+        assert!(eval_with("1 ? 2", &[]).is_err());
+    }
+}
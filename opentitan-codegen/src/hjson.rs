@@ -0,0 +1,731 @@
+//! Hjson front end for the OpenTitan description files
+//!
+//! The files are parsed into a tree of values that remember their line, which is then
+//! deserialized with serde. This allows errors to report the line & key they occurred at.
+//!
+//! Numbers & booleans are kept as written, so they can be deserialized into strings. reggen
+//! accepts `resval: 0` as well as `resval: "0"`, the description structs only use strings.
+
+use std::{fmt, fs, marker::PhantomData, path::Path};
+
+use serde::{
+    de::{
+        self, value::StrDeserializer, DeserializeOwned, DeserializeSeed, IntoDeserializer,
+        MapAccess, SeqAccess, Visitor,
+    },
+    Deserialize,
+};
+
+/// Reads & deserializes the hjson file at `path`
+///
+/// The returned error message contains the file, line & key the error occurred at.
+pub fn from_file<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|error| format!("Can not read file {}: {}", path.display(), error))?;
+    from_str(&content).map_err(|error| format!("{}:{}", path.display(), error))
+}
+
+/// Deserializes the hjson document `s`
+pub fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Error> {
+    let value = Parser::new(s).parse_root()?;
+    T::deserialize(ValueDeserializer {
+        value: &value,
+        path: String::new(),
+    })
+}
+
+/// Object that keeps its members in the order they were written, unlike the std maps
+#[derive(Debug)]
+pub struct OrderedMap<V>(pub Vec<(String, V)>);
+
+impl<'de, V: Deserialize<'de>> Deserialize<'de> for OrderedMap<V> {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(OrderedMapVisitor(PhantomData))
+    }
+}
+
+struct OrderedMapVisitor<V>(PhantomData<V>);
+
+impl<'de, V: Deserialize<'de>> Visitor<'de> for OrderedMapVisitor<V> {
+    type Value = OrderedMap<V>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut members = vec![];
+        while let Some(member) = map.next_entry()? {
+            members.push(member);
+        }
+        Ok(OrderedMap(members))
+    }
+}
+
+/// Error while parsing or deserializing an hjson document
+#[derive(Debug)]
+pub struct Error {
+    line: usize,
+    path: String,
+    message: String,
+}
+
+impl Error {
+    /// Sets the location of the error, unless it was already located more precisely
+    fn locate(mut self, line: usize, path: &str) -> Self {
+        if self.line == 0 {
+            self.line = line;
+            self.path = path.to_owned();
+        }
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}: {}", self.line, self.message)
+        } else {
+            write!(f, "{}: `{}`: {}", self.line, self.path, self.message)
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error {
+            line: 0,
+            path: String::new(),
+            message: msg.to_string(),
+        }
+    }
+}
+
+/// Parsed hjson value & the line it starts at
+#[derive(Debug)]
+struct Value {
+    kind: ValueKind,
+    line: usize,
+}
+
+#[derive(Debug)]
+enum ValueKind {
+    Null,
+    Bool(bool),
+    /// Numbers keep their text, they are only interpreted during deserialization
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    /// Keys leading to the value currently parsed, used for error messages
+    path: Vec<String>,
+}
+
+impl Parser {
+    fn new(source: &str) -> Self {
+        Parser {
+            chars: source.chars().collect(),
+            pos: 0,
+            line: 1,
+            path: vec![],
+        }
+    }
+
+    fn error(&self, message: impl fmt::Display) -> Error {
+        Error {
+            line: self.line,
+            path: self.path.iter().fold(String::new(), |path, key| {
+                if path.is_empty() || key.starts_with('[') {
+                    path + key
+                } else {
+                    path + "." + key
+                }
+            }),
+            message: message.to_string(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars()
+            .enumerate()
+            .all(|(i, c)| self.peek_at(i) == Some(c))
+    }
+
+    /// Skips whitespace & comments
+    fn skip_whitespace(&mut self) -> Result<(), Error> {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.next();
+                }
+                Some('#') => self.skip_line(),
+                Some('/') if self.peek_at(1) == Some('/') => self.skip_line(),
+                Some('/') if self.peek_at(1) == Some('*') => {
+                    let line = self.line;
+                    self.pos += 2;
+                    while !self.starts_with("*/") {
+                        if self.next().is_none() {
+                            return Err(Error {
+                                line,
+                                ..self.error("unterminated block comment")
+                            });
+                        }
+                    }
+                    self.pos += 2;
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while !matches!(self.peek(), None | Some('\n')) {
+            self.next();
+        }
+    }
+
+    /// Parses the document, the braces of the root object may be omitted
+    fn parse_root(&mut self) -> Result<Value, Error> {
+        self.skip_whitespace()?;
+        let value = match self.peek() {
+            Some('{') | Some('[') => self.parse_value()?,
+            _ => {
+                let line = self.line;
+                Value {
+                    kind: ValueKind::Object(self.parse_members(None)?),
+                    line,
+                }
+            }
+        };
+        self.skip_whitespace()?;
+        match self.peek() {
+            None => Ok(value),
+            Some(c) => Err(self.error(format!("unexpected `{}` after the root value", c))),
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value, Error> {
+        self.skip_whitespace()?;
+        let line = self.line;
+        let kind = match self.peek() {
+            None => return Err(self.error("unexpected end of file, expected a value")),
+            Some('{') => {
+                self.next();
+                ValueKind::Object(self.parse_members(Some('}'))?)
+            }
+            Some('[') => {
+                self.next();
+                ValueKind::Array(self.parse_elements()?)
+            }
+            Some('\'') if self.starts_with("'''") => ValueKind::String(self.parse_multiline()?),
+            Some(quote @ ('"' | '\'')) => {
+                self.next();
+                ValueKind::String(self.parse_quoted(quote)?)
+            }
+            Some(c @ (',' | ':' | ']' | '}')) => {
+                return Err(self.error(format!("unexpected `{}`, expected a value", c)))
+            }
+            Some(_) => self.parse_quoteless(),
+        };
+        Ok(Value { kind, line })
+    }
+
+    /// Parses the members of an object up to the closing `end`, or the end of the file
+    fn parse_members(&mut self, end: Option<char>) -> Result<Vec<(String, Value)>, Error> {
+        let start = self.line;
+        let mut members = vec![];
+        loop {
+            self.skip_whitespace()?;
+            match (self.peek(), end) {
+                (None, None) => return Ok(members),
+                (None, Some(_)) => {
+                    return Err(Error {
+                        line: start,
+                        ..self.error("unterminated object")
+                    })
+                }
+                (Some(c), Some(end)) if c == end => {
+                    self.next();
+                    return Ok(members);
+                }
+                _ => {}
+            }
+
+            let key = self.parse_key()?;
+            self.skip_whitespace()?;
+            if self.next() != Some(':') {
+                return Err(self.error(format!("expected `:` after key `{}`", key)));
+            }
+            self.path.push(key.clone());
+            let value = self.parse_value()?;
+            self.path.pop();
+            members.push((key, value));
+
+            self.skip_separator()?;
+        }
+    }
+
+    fn parse_elements(&mut self) -> Result<Vec<Value>, Error> {
+        let start = self.line;
+        let mut elements = vec![];
+        loop {
+            self.skip_whitespace()?;
+            match self.peek() {
+                None => {
+                    return Err(Error {
+                        line: start,
+                        ..self.error("unterminated array")
+                    })
+                }
+                Some(']') => {
+                    self.next();
+                    return Ok(elements);
+                }
+                _ => {}
+            }
+            self.path.push(format!("[{}]", elements.len()));
+            elements.push(self.parse_value()?);
+            self.path.pop();
+
+            self.skip_separator()?;
+        }
+    }
+
+    /// Skips the optional comma between members or elements
+    fn skip_separator(&mut self) -> Result<(), Error> {
+        self.skip_whitespace()?;
+        if self.peek() == Some(',') {
+            self.next();
+        }
+        Ok(())
+    }
+
+    fn parse_key(&mut self) -> Result<String, Error> {
+        match self.peek() {
+            Some(quote @ ('"' | '\'')) => {
+                self.next();
+                self.parse_quoted(quote)
+            }
+            _ => {
+                let mut key = String::new();
+                while let Some(c) = self.peek() {
+                    if c == ':' {
+                        break;
+                    }
+                    // Whitespace is only allowed between the key & the colon
+                    if matches!(c, ' ' | '\t') {
+                        let mut rest = self.chars[self.pos..].iter();
+                        if rest.find(|c| !matches!(c, ' ' | '\t')) == Some(&':') {
+                            while self.peek() != Some(':') {
+                                self.next();
+                            }
+                            break;
+                        }
+                    }
+                    if c.is_whitespace() || matches!(c, ',' | '[' | ']' | '{' | '}') {
+                        return Err(self.error(format!(
+                            "unexpected `{}` in key `{}`, keys with these characters need quotes",
+                            c.escape_default(),
+                            key
+                        )));
+                    }
+                    key.push(c);
+                    self.next();
+                }
+                if key.is_empty() {
+                    return Err(self.error("expected a key"));
+                }
+                Ok(key)
+            }
+        }
+    }
+
+    /// Parses a string after its opening `quote`
+    fn parse_quoted(&mut self, quote: char) -> Result<String, Error> {
+        let mut s = String::new();
+        loop {
+            let c = match self.peek() {
+                None | Some('\n') => return Err(self.error("unterminated string")),
+                Some(c) => c,
+            };
+            self.next();
+            match c {
+                c if c == quote => return Ok(s),
+                '\\' => {
+                    let c = match self.next() {
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('u') => {
+                            let hex: String = (0..4).filter_map(|_| self.next()).collect();
+                            u32::from_str_radix(&hex, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| {
+                                    self.error(format!("invalid unicode escape `\\u{}`", hex))
+                                })?
+                        }
+                        Some(c @ ('"' | '\'' | '\\' | '/')) => c,
+                        Some(c) => {
+                            return Err(self.error(format!("invalid escape `\\{}`", c)));
+                        }
+                        None => return Err(self.error("unterminated string")),
+                    };
+                    s.push(c);
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    /// Parses a `'''` string, the indentation of the opening quotes is removed from every line
+    fn parse_multiline(&mut self) -> Result<String, Error> {
+        let start = self.line;
+        let line_start = self.chars[..self.pos]
+            .iter()
+            .rposition(|c| *c == '\n')
+            .map_or(0, |i| i + 1);
+        let indent = self.pos - line_start;
+        self.pos += 3;
+
+        // Whitespace & the line break directly after the opening quotes are ignored
+        while matches!(self.peek(), Some(c) if c != '\n' && c.is_whitespace()) {
+            self.next();
+        }
+        if self.peek() == Some('\n') {
+            self.next();
+        }
+
+        let mut s = String::new();
+        let mut column = 0;
+        loop {
+            if self.starts_with("'''") {
+                self.pos += 3;
+                break;
+            }
+            match self.next() {
+                None => {
+                    return Err(Error {
+                        line: start,
+                        ..self.error("unterminated multiline string")
+                    })
+                }
+                Some('\n') => {
+                    s.push('\n');
+                    column = 0;
+                }
+                Some(c) if column < indent && c != '\r' && c.is_whitespace() => column += 1,
+                Some(c) => {
+                    s.push(c);
+                    column = usize::MAX;
+                }
+            }
+        }
+
+        // Whitespace of the line holding the closing quotes & the last line break are ignored
+        let trimmed = s.trim_end_matches(|c: char| c != '\n' && c.is_whitespace());
+        let trimmed = trimmed.strip_suffix('\n').unwrap_or(trimmed);
+        Ok(trimmed.trim_end_matches('\r').to_owned())
+    }
+
+    /// Parses a literal or a quoteless string, which extends to the end of the line
+    fn parse_quoteless(&mut self) -> ValueKind {
+        let rest_len = self.chars[self.pos..]
+            .iter()
+            .position(|c| *c == '\n')
+            .unwrap_or(self.chars.len() - self.pos);
+        let rest: String = self.chars[self.pos..self.pos + rest_len].iter().collect();
+
+        // Literals end at a separator or comment, strings with the same start do not
+        let literal_len = rest
+            .find([',', ']', '}', '#'])
+            .into_iter()
+            .chain(rest.find("//"))
+            .chain(rest.find("/*"))
+            .min()
+            .unwrap_or(rest.len());
+        let literal = rest[..literal_len].trim_end();
+        let kind = match literal {
+            "true" => Some(ValueKind::Bool(true)),
+            "false" => Some(ValueKind::Bool(false)),
+            "null" => Some(ValueKind::Null),
+            number if is_number(number) => Some(ValueKind::Number(number.to_owned())),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            self.pos += literal.chars().count();
+            return kind;
+        }
+
+        self.pos += rest_len;
+        ValueKind::String(rest.trim().to_owned())
+    }
+}
+
+/// Whether `s` is a number following the JSON grammar
+fn is_number(s: &str) -> bool {
+    let s = s.strip_prefix('-').unwrap_or(s);
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+        None => (s, None),
+    };
+    let (integer, fraction) = match mantissa.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (mantissa, None),
+    };
+    let digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+
+    digits(integer)
+        && (integer == "0" || !integer.starts_with('0'))
+        && fraction.is_none_or(digits)
+        && exponent.is_none_or(|e| digits(e.strip_prefix(['+', '-']).unwrap_or(e)))
+}
+
+struct ValueDeserializer<'a> {
+    value: &'a Value,
+    /// Keys leading to the value, used for error messages
+    path: String,
+}
+
+impl<'a> ValueDeserializer<'a> {
+    fn child(&self, value: &'a Value, key: impl fmt::Display) -> Self {
+        let path = if self.path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", self.path, key)
+        };
+        ValueDeserializer { value, path }
+    }
+
+    fn invalid_type(&self, expected: &dyn de::Expected) -> Error {
+        let unexpected = match &self.value.kind {
+            ValueKind::Null => de::Unexpected::Unit,
+            ValueKind::Bool(b) => de::Unexpected::Bool(*b),
+            ValueKind::Number(n) => de::Unexpected::Other(n),
+            ValueKind::String(s) => de::Unexpected::Str(s),
+            ValueKind::Array(_) => de::Unexpected::Seq,
+            ValueKind::Object(_) => de::Unexpected::Map,
+        };
+        <Error as de::Error>::invalid_type(unexpected, expected).locate(self.value.line, &self.path)
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for ValueDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let result = match &self.value.kind {
+            ValueKind::Null => visitor.visit_unit(),
+            ValueKind::Bool(b) => visitor.visit_bool(*b),
+            ValueKind::Number(n) => {
+                if let Ok(n) = n.parse::<u64>() {
+                    visitor.visit_u64(n)
+                } else if let Ok(n) = n.parse::<i64>() {
+                    visitor.visit_i64(n)
+                } else {
+                    match n.parse::<f64>() {
+                        Ok(n) => visitor.visit_f64(n),
+                        Err(_) => visitor.visit_str(n),
+                    }
+                }
+            }
+            ValueKind::String(s) => visitor.visit_str(s),
+            ValueKind::Array(elements) => visitor.visit_seq(Elements {
+                elements: elements.iter().enumerate(),
+                deserializer: &self,
+            }),
+            ValueKind::Object(members) => visitor.visit_map(Members {
+                members: members.iter(),
+                value: None,
+                deserializer: &self,
+            }),
+        };
+        result.map_err(|error| error.locate(self.value.line, &self.path))
+    }
+
+    /// Numbers & booleans are accepted as strings in the form they were written
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let result: Result<_, Error> = match &self.value.kind {
+            ValueKind::Bool(b) => visitor.visit_str(if *b { "true" } else { "false" }),
+            ValueKind::Number(n) | ValueKind::String(n) => visitor.visit_str(n),
+            _ => return Err(self.invalid_type(&visitor)),
+        };
+        result.map_err(|error| error.locate(self.value.line, &self.path))
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value.kind {
+            ValueKind::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char bytes byte_buf unit
+        unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier
+    }
+}
+
+struct Elements<'a, 'b> {
+    elements: std::iter::Enumerate<std::slice::Iter<'a, Value>>,
+    deserializer: &'b ValueDeserializer<'a>,
+}
+
+impl<'de, 'a, 'b> SeqAccess<'de> for Elements<'a, 'b> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        let Some((index, value)) = self.elements.next() else {
+            return Ok(None);
+        };
+        let deserializer = ValueDeserializer {
+            value,
+            path: format!("{}[{}]", self.deserializer.path, index),
+        };
+        seed.deserialize(deserializer).map(Some)
+    }
+}
+
+struct Members<'a, 'b> {
+    members: std::slice::Iter<'a, (String, Value)>,
+    value: Option<(&'a str, &'a Value)>,
+    deserializer: &'b ValueDeserializer<'a>,
+}
+
+impl<'de, 'a, 'b> MapAccess<'de> for Members<'a, 'b> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let Some((key, value)) = self.members.next() else {
+            return Ok(None);
+        };
+        self.value = Some((key, value));
+        let key: StrDeserializer<Error> = key.as_str().into_deserializer();
+        seed.deserialize(key).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let (key, value) = self
+            .value
+            .take()
+            .expect("next_value_seed called before next_key_seed");
+        seed.deserialize(self.deserializer.child(value, key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Deserialize, Debug)]
+    struct Description {
+        name: String,
+        desc: String,
+        resval: Option<String>,
+        local: Option<String>,
+        fields: Vec<Field>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct Field {
+        bits: String,
+        name: Option<String>,
+    }
+
+    #[test]
+    fn opentitan_constructs() {
+        let description: Description = from_str(
+            r#"
+            // Comment
+            { name: loop_counter   # quoteless string
+              desc: '''
+                    First line
+                      Indented line
+                    '''
+              /* block
+                 comment */
+              resval : 0,
+              local: true
+              fields: [
+                { bits: "0", name: 'LOOP' }
+                { bits: "7:1" },
+              ]
+            }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(description.name, "loop_counter   # quoteless string");
+        assert_eq!(description.desc, "First line\n  Indented line");
+        assert_eq!(description.resval.as_deref(), Some("0"));
+        assert_eq!(description.local.as_deref(), Some("true"));
+        assert_eq!(description.fields.len(), 2);
+        assert_eq!(description.fields[0].name.as_deref(), Some("LOOP"));
+        assert_eq!(description.fields[1].bits, "7:1");
+    }
+
+    #[test]
+    fn single_line_multiline_string() {
+        let description: Description =
+            from_str("name: x\ndesc: '''single line'''\nfields: []").unwrap();
+        assert_eq!(description.desc, "single line");
+    }
+
+    #[test]
+    fn error_location() {
+        let error = from_str::<Description>(
+            "{\n  name: x\n  desc: y\n  fields: [\n    { name: \"a\" }\n  ]\n}",
+        )
+        .unwrap_err();
+        assert_eq!(error.line, 5);
+        assert_eq!(error.path, "fields[0]");
+        assert!(error.message.contains("bits"));
+
+        let error = from_str::<Description>("{\n  name: \"x\n}").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert_eq!(error.path, "name");
+    }
+}
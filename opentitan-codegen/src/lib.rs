@@ -0,0 +1,36 @@
+//! Code generation from the hjson descriptions of the OpenTitan repository
+//!
+//! This is shared by the `opentitan-macros` proc macros and the `opentitan-regtool` binary, which
+//! writes the same code to source files so builds do not need an OpenTitan checkout. The build
+//! script of `opentitan-lib` also uses it to generate the memory map of the linker script & to
+//! find the IPs of the selected top, the regtool to export the register map as a CMSIS-SVD file.
+
+use std::{env, path::PathBuf};
+
+mod addresses;
+mod expr;
+mod hjson;
+mod memory;
+mod names;
+mod registers;
+mod svd;
+
+pub use addresses::{addresses, ip_types};
+pub use memory::memory_map;
+pub use registers::registers;
+pub use svd::svd;
+
+/// Returns the path of the OpenTitan repository
+///
+/// This is `OPENTITAN_PATH` if set, otherwise the `opentitan` directory next to the crate.
+pub fn get_opentitan_path() -> PathBuf {
+    if let Ok(ot_path) = env::var("OPENTITAN_PATH") {
+        PathBuf::from(ot_path)
+    } else {
+        let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".into());
+        let mut path = PathBuf::from(manifest_dir);
+        path.pop();
+        path.push("opentitan");
+        path
+    }
+}
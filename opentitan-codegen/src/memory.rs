@@ -0,0 +1,147 @@
+use std::path::Path;
+
+use crate::{
+    addresses::{ModuleDescription, TopDescription},
+    expr::parse_int,
+    hjson::{self, OrderedMap},
+};
+
+/// Memory of a top, as region of the linker script
+#[derive(Debug, PartialEq)]
+struct Region {
+    name: String,
+    base: u64,
+    size: u64,
+    exec: bool,
+}
+
+/// Generates the `MEMORY` command of a linker script with all memories of the top described at
+/// `top_path`
+///
+/// The regions are named after the labels of the memories, eg. `eflash` or `ram_main`. The path
+/// of the top description is relative to the OpenTitan repository at `opentitan_path`.
+pub fn memory_map(opentitan_path: &Path, top_path: &str) -> Result<String, String> {
+    let top = hjson::from_file::<TopDescription>(&opentitan_path.join(top_path))?;
+    let regions = regions(&top).map_err(|error| format!("{}: {}", top_path, error))?;
+
+    let width = regions.iter().map(|r| r.name.len()).max().unwrap_or(0);
+    let mut out = format!("/* Memories of {} */\nMEMORY\n{{\n", top_path);
+    for region in regions {
+        out.push_str(&format!(
+            "  {:width$} {:5} : ORIGIN = {:#x}, LENGTH = {:#x}\n",
+            region.name,
+            if region.exec { "(rx)" } else { "(!rx)" },
+            region.base,
+            region.size,
+            width = width
+        ));
+    }
+    out.push_str("}\n");
+    Ok(out)
+}
+
+/// Lists the memories of the modules, followed by the memories of older tops that are listed
+/// separately
+fn regions(top: &TopDescription) -> Result<Vec<Region>, String> {
+    let number =
+        |s: &str, what: &str| parse_int(s).ok_or_else(|| format!("invalid {} \"{}\"", what, s));
+    let exec = |exec: &Option<String>| {
+        exec.as_deref()
+            .is_some_and(|exec| exec.eq_ignore_ascii_case("true"))
+    };
+
+    let mut regions = vec![];
+    for module in &top.module {
+        let Some(OrderedMap(memories)) = &module.memory else {
+            continue;
+        };
+        for (interface, memory) in memories {
+            let name = memory
+                .label
+                .clone()
+                .unwrap_or_else(|| format!("{}_{}", module.name, interface));
+            let base = base_addr(module, interface).ok_or_else(|| {
+                format!(
+                    "module {} has no base address for the memory {}",
+                    module.name, interface
+                )
+            })?;
+            regions.push(Region {
+                base: number(base, &format!("base address of memory {}", name))?,
+                size: number(&memory.size, &format!("size of memory {}", name))?,
+                exec: exec(&memory.exec),
+                name,
+            });
+        }
+    }
+    for memory in top.memory.iter().flatten() {
+        regions.push(Region {
+            name: memory.name.clone(),
+            base: number(
+                &memory.base_addr,
+                &format!("base address of memory {}", memory.name),
+            )?,
+            size: number(&memory.size, &format!("size of memory {}", memory.name))?,
+            exec: exec(&memory.exec),
+        });
+    }
+    Ok(regions)
+}
+
+fn base_addr<'a>(module: &'a ModuleDescription, interface: &str) -> Option<&'a str> {
+    match &module.base_addrs {
+        Some(OrderedMap(bases)) => bases
+            .iter()
+            .find(|(name, _)| name == interface)
+            .map(|(_, base)| base.0.as_str()),
+        None => module.base_addr.as_deref(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memories() {
+        let top = hjson::from_str::<TopDescription>(
+            r#"{
+                module: [
+                    { name: "uart0", type: "uart", base_addr: "0x40000000" },
+                    { name: "sram_ctrl_main", type: "sram_ctrl",
+                      base_addrs: { regs: "0x411c0000", ram: { hart: "0x10000000" } },
+                      memory: { ram: { label: "ram_main", exec: "True", size: "0x20000" } } },
+                    { name: "flash_ctrl", type: "flash_ctrl",
+                      base_addrs: { core: "0x41000000", mem: "0x20000000" },
+                      memory: { mem: { size: "0x100000" } } },
+                ],
+                memory: [{ name: "rom", base_addr: "0x8000", size: "0x8000", exec: "False" }],
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            regions(&top).unwrap(),
+            [
+                Region {
+                    name: "ram_main".to_owned(),
+                    base: 0x1000_0000,
+                    size: 0x2_0000,
+                    exec: true,
+                },
+                Region {
+                    name: "flash_ctrl_mem".to_owned(),
+                    base: 0x2000_0000,
+                    size: 0x10_0000,
+                    exec: false,
+                },
+                Region {
+                    name: "rom".to_owned(),
+                    base: 0x8000,
+                    size: 0x8000,
+                    exec: false,
+                },
+            ]
+        );
+    }
+}
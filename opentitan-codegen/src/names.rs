@@ -0,0 +1,47 @@
+//! Conversion of the names of the hjson descriptions to rust naming conventions
+
+/// Converts a snake case name of an hjson description to camel case, eg. `tx_watermark` to
+/// `TxWatermark`
+pub fn camel_case(name: &str) -> String {
+    name.split('_')
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase())
+                .into_iter()
+                .chain(chars.map(|c| c.to_ascii_lowercase()))
+        })
+        .collect()
+}
+
+/// Converts a parameter name like `NumAlerts` to a constant name like `NUM_ALERTS`
+pub fn screaming_snake_case(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut out = String::new();
+    for (index, c) in chars.iter().enumerate() {
+        let prev = index.checked_sub(1).map(|index| chars[index]);
+        let next = chars.get(index + 1);
+        // Words start at an uppercase letter after a lowercase one or a digit, or at the last
+        // uppercase letter of an acronym, eg. `SBoxImpl` is split into `S`, `Box` & `Impl`
+        let word_start = c.is_ascii_uppercase()
+            && prev.is_some_and(|prev| {
+                prev.is_ascii_lowercase()
+                    || prev.is_ascii_digit()
+                    || (prev.is_ascii_uppercase() && next.is_some_and(char::is_ascii_lowercase))
+            });
+        if word_start {
+            out.push('_');
+        }
+        out.push(c.to_ascii_uppercase());
+    }
+    out
+}
+
+/// Whether the alert `name` is fatal
+///
+/// reggen names recoverable alerts `recov_*` & fatal ones `fatal_*`, alerts that follow neither
+/// convention are treated as fatal.
+pub fn is_fatal_alert(name: &str) -> bool {
+    !name.to_ascii_lowercase().starts_with("recov")
+}
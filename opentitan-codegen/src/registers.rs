@@ -0,0 +1,3122 @@
+use std::{
+    borrow::Borrow,
+    env,
+    fmt::Display,
+    path::{Path, PathBuf},
+    vec,
+};
+
+use proc_macro2::{Ident, Span};
+use quote::{format_ident, quote};
+use serde::{
+    de::{
+        value::{MapAccessDeserializer, SeqAccessDeserializer},
+        DeserializeOwned, MapAccess, SeqAccess, Visitor,
+    },
+    Deserialize,
+};
+use syn::{
+    parenthesized,
+    parse::{self, Parse, ParseStream},
+    Expr, ItemStruct, LitStr, Token,
+};
+
+use crate::{
+    expr::{self, parse_int},
+    hjson::{self, OrderedMap},
+    names::{camel_case, is_fatal_alert, screaming_snake_case},
+};
+
+// TODO: Refactor and make more robust by using reggen from opentitan
+
+/// Generates the register block of the `#[registers(...)]` attribute with the arguments `args`
+/// on the struct `item`
+///
+/// OpenTitan paths of IP descriptions are relative to the repository at `opentitan_path`, crate
+/// paths to the `CARGO_MANIFEST_DIR` of the crate using the attribute.
+pub fn registers(
+    opentitan_path: &Path,
+    args: proc_macro2::TokenStream,
+    item: proc_macro2::TokenStream,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let args = syn::parse2::<RegistersArgs>(args)?;
+
+    // Read & Parse .hjson file of IP description
+    let mut ip = args.source.read::<IPDescription>(opentitan_path)?;
+
+    // Replace the parameter defaults by the values given in the attribute
+    let name = args.source.name();
+    override_params(&name, &mut ip.param_list, args.params)?;
+
+    let input_struct = syn::parse2::<ItemStruct>(item)?;
+    let ctx = Context::new(args.source.span(), Path::new(&name));
+    let registers = gen_registers(&ctx, ip, input_struct)?;
+    let track = args.source.track()?;
+    Ok(quote!(
+        #track
+        #registers
+    ))
+}
+
+/// Returns the number of bytes used by the register interface `interface` of the IP described at
+/// `ip_path`, `None` if the IP has no such interface
+///
+/// IPs with a single interface use it for any name, the interrupt & alert registers belong to the
+/// first interface.
+pub(crate) fn interface_size(
+    span: Span,
+    opentitan_path: &Path,
+    ip_path: &Path,
+    interface: &str,
+) -> syn::Result<Option<usize>> {
+    let ip = hjson::from_file::<BlockDescription>(&opentitan_path.join(ip_path))
+        .map_err(|error| parse::Error::new(span, error))?;
+
+    let ctx = Context::new(span, ip_path);
+    let Some((ctx, registers, first)) = select_interface(&ctx, &ip, interface) else {
+        return Ok(None);
+    };
+
+    let mut size = 0;
+    if first {
+        gen_interrupt_alert_registers(
+            &ctx,
+            &mut size,
+            ip.interrupt_list.is_some(),
+            !ip.alert_list.is_empty(),
+        )?;
+    }
+    for (index, reg_desc) in registers.iter().enumerate() {
+        gen_register(
+            &ctx.register(index, reg_desc),
+            &mut size,
+            reg_desc,
+            &ip.param_list,
+        )?;
+    }
+    Ok(Some(size))
+}
+
+/// Returns the interface with the alert test register of the IP described at `ip_path`, `None`
+/// for IPs with a single interface, & the offset of the register inside it
+///
+/// Only valid for IPs with alerts, others don't have an alert test register.
+pub(crate) fn alert_test_register(
+    span: Span,
+    opentitan_path: &Path,
+    ip_path: &Path,
+) -> syn::Result<(Option<String>, usize)> {
+    let ip = hjson::from_file::<BlockDescription>(&opentitan_path.join(ip_path))
+        .map_err(|error| parse::Error::new(span, error))?;
+
+    let interface = match &ip.registers {
+        RegisterBlocks::Single(_) => None,
+        RegisterBlocks::Interfaces(OrderedMap(interfaces)) => {
+            interfaces.first().map(|(name, _)| name.clone())
+        }
+    };
+    // The alert test register is the last of the common registers
+    let mut size = 0;
+    let ctx = Context::new(span, ip_path);
+    gen_interrupt_alert_registers(&ctx, &mut size, ip.interrupt_list.is_some(), true)?;
+    Ok((interface, size - 4))
+}
+
+/// Lists the registers of the interface `interface` of the IP described at `ip_path`, `None` if
+/// the IP has no such interface
+///
+/// The parameters of the IP are overridden by `params`, like by the arguments of the
+/// `#[registers]` attribute.
+pub(crate) fn interface_registers(
+    span: Span,
+    opentitan_path: &Path,
+    ip_path: &Path,
+    interface: &str,
+    params: Vec<(Ident, Expr)>,
+) -> syn::Result<Option<Vec<MapRegister>>> {
+    let mut ip = hjson::from_file::<BlockDescription>(&opentitan_path.join(ip_path))
+        .map_err(|error| parse::Error::new(span, error))?;
+    override_params(&ip_path.display().to_string(), &mut ip.param_list, params)?;
+
+    map_registers(&Context::new(span, ip_path), &ip, interface)
+}
+
+/// Returns the register list of `interface` & whether it is the first interface of `ip`
+fn select_interface<'a>(
+    ctx: &Context,
+    ip: &'a BlockDescription,
+    interface: &str,
+) -> Option<(Context, &'a [RegisterDescription], bool)> {
+    match &ip.registers {
+        RegisterBlocks::Single(registers) => Some((ctx.clone(), registers, true)),
+        RegisterBlocks::Interfaces(OrderedMap(interfaces)) => {
+            let index = interfaces.iter().position(|(name, _)| name == interface)?;
+            Some((ctx.interface(interface), &interfaces[index].1, index == 0))
+        }
+    }
+}
+
+/// Register of a register interface, as listed for descriptions of the whole register map
+#[derive(Debug, PartialEq)]
+pub(crate) struct MapRegister {
+    pub(crate) name: String,
+    pub(crate) desc: Option<String>,
+    pub(crate) offset: usize,
+    pub(crate) swaccess: String,
+    /// Reset value & mask of the bits with a known reset value
+    pub(crate) reset: (u32, u32),
+    pub(crate) fields: Vec<MapField>,
+    /// Number of words of a window, which is listed as a single register
+    pub(crate) words: Option<usize>,
+}
+
+/// Field of a [`MapRegister`]
+#[derive(Debug, PartialEq)]
+pub(crate) struct MapField {
+    pub(crate) name: String,
+    pub(crate) desc: Option<String>,
+    pub(crate) start: usize,
+    pub(crate) width: usize,
+    /// Access of the field, if it differs from the one of the register
+    pub(crate) swaccess: Option<String>,
+    /// Name, description & value of the enumerated values
+    pub(crate) values: Vec<(String, String, u32)>,
+}
+
+/// Lists the registers of `interface` at the offsets of the generated register block
+///
+/// Multiregs are split into their registers like by reggen, eg. `ALERT_EN_0` & `ALERT_EN_1`, and
+/// the copies of compacted fields are numbered across the registers.
+fn map_registers(
+    ctx: &Context,
+    ip: &BlockDescription,
+    interface: &str,
+) -> syn::Result<Option<Vec<MapRegister>>> {
+    let interrupt_bits = interrupt_bits(ctx, ip.interrupt_list.as_deref().unwrap_or_default())?;
+    let Some((ctx, registers, first)) = select_interface(ctx, ip, interface) else {
+        return Ok(None);
+    };
+
+    let mut map = vec![];
+    let mut offset = 0;
+    if first {
+        let bit_fields = |list: &[InterruptDescription], bits: &[(usize, usize)]| {
+            list.iter()
+                .zip(bits)
+                .map(|(signal, (start, width))| MapField {
+                    name: signal.name.to_uppercase(),
+                    desc: Some(signal.desc.clone()),
+                    start: *start,
+                    width: *width,
+                    swaccess: None,
+                    values: vec![],
+                })
+                .collect::<Vec<_>>()
+        };
+        let alert_bits = (0..ip.alert_list.len())
+            .map(|bit| (bit, 1))
+            .collect::<Vec<_>>();
+        let mut add = |name: &str, desc: &str, swaccess: &str, fields| {
+            let offset = map.len() * 4;
+            map.push(MapRegister {
+                name: name.to_owned(),
+                desc: Some(desc.to_owned()),
+                offset,
+                swaccess: swaccess.to_owned(),
+                reset: (0, u32::MAX),
+                fields,
+                words: None,
+            })
+        };
+        if let Some(interrupts) = &ip.interrupt_list {
+            add(
+                "INTR_STATE",
+                "Interrupt State Register",
+                "rw1c",
+                bit_fields(interrupts, &interrupt_bits),
+            );
+            add(
+                "INTR_ENABLE",
+                "Interrupt Enable Register",
+                "rw",
+                bit_fields(interrupts, &interrupt_bits),
+            );
+            add(
+                "INTR_TEST",
+                "Interrupt Test Register",
+                "wo",
+                bit_fields(interrupts, &interrupt_bits),
+            );
+        }
+        if !ip.alert_list.is_empty() {
+            add(
+                "ALERT_TEST",
+                "Alert Test Register",
+                "wo",
+                bit_fields(&ip.alert_list, &alert_bits),
+            );
+        }
+        offset = map.len() * 4;
+    }
+
+    for (index, reg_desc) in registers.iter().enumerate() {
+        let ctx = ctx.register(index, reg_desc);
+        // The offsets follow the generated register block, which also checks the description
+        let address = offset;
+        gen_register(&ctx, &mut offset, reg_desc, &ip.param_list)?;
+
+        match reg_desc {
+            RegisterDescription {
+                name: Some(name),
+                swaccess: Some(swaccess),
+                desc,
+                fields,
+                resval,
+                ..
+            } => map.push(MapRegister {
+                name: name.to_uppercase(),
+                desc: desc.clone(),
+                offset: address,
+                swaccess: swaccess.clone(),
+                reset: map_reset(&ctx, fields, resval, 1, false)?,
+                fields: map_fields(&ctx, fields.as_deref().unwrap_or_default(), name, 1, None)?,
+                words: None,
+            }),
+            RegisterDescription {
+                multireg: Some(multireg),
+                ..
+            } => {
+                let layout = MultiregLayout::new(&ctx, multireg, &ip.param_list)?;
+                let fields = multireg.fields.as_deref().unwrap_or_default();
+                for reg in 0..layout.registers() {
+                    let copies = if layout.compact {
+                        (layout.count - reg * layout.per_reg).min(layout.per_reg)
+                    } else {
+                        1
+                    };
+                    let name = match layout.array_len() {
+                        Some(_) => format!("{}_{}", multireg.name, reg),
+                        None => multireg.name.clone(),
+                    };
+                    // Copies are only numbered if the multireg has several
+                    let first_copy =
+                        (layout.compact && layout.count > 1).then_some(reg * layout.per_reg);
+                    map.push(MapRegister {
+                        name: name.to_uppercase(),
+                        desc: multireg.desc.clone(),
+                        offset: address + 4 * reg,
+                        swaccess: multireg.swaccess.clone(),
+                        reset: map_reset(
+                            &ctx,
+                            &multireg.fields,
+                            &multireg.resval,
+                            copies,
+                            layout.compact,
+                        )?,
+                        fields: map_fields(&ctx, fields, &multireg.name, copies, first_copy)?,
+                        words: None,
+                    });
+                }
+            }
+            RegisterDescription {
+                window: Some(window),
+                ..
+            } => map.push(MapRegister {
+                name: window.name.to_uppercase(),
+                desc: window.desc.clone(),
+                offset: address,
+                swaccess: window.swaccess.clone(),
+                reset: (0, 0),
+                fields: vec![],
+                words: Some((offset - address) / 4),
+            }),
+            _ => {}
+        }
+    }
+    Ok(Some(map))
+}
+
+/// Reset value & mask of a register, registers without fields only have the reset value of the
+/// register
+fn map_reset(
+    ctx: &Context,
+    fields: &Option<Vec<FieldDescription>>,
+    resval: &Option<String>,
+    copies: usize,
+    compact: bool,
+) -> syn::Result<(u32, u32)> {
+    match fields {
+        Some(fields) => reset_value(ctx, fields, resval.as_deref(), copies, compact),
+        None => Ok(match resval {
+            Some(resval) => match parse_resval(&ctx.key("resval"), resval)? {
+                Some(resval) => (resval as u32, u32::MAX),
+                None => (0, 0),
+            },
+            None => (0, u32::MAX),
+        }),
+    }
+}
+
+/// Lists the fields of a register, with `copies` copies of each field
+///
+/// Unnamed fields are named after the register. Copies are numbered starting at `first_copy`,
+/// `None` keeps the names of single fields.
+fn map_fields(
+    ctx: &Context,
+    fields: &[FieldDescription],
+    reg_name: &str,
+    copies: usize,
+    first_copy: Option<usize>,
+) -> syn::Result<Vec<MapField>> {
+    let mut map = vec![];
+    for (index, f_desc) in fields.iter().enumerate() {
+        let ctx = ctx.field(index, f_desc);
+        let (start, width) = bit_range(&ctx.key("bits"), &f_desc.bits)?;
+        let name = f_desc.name.as_deref().unwrap_or(reg_name).to_uppercase();
+        let values = f_desc
+            .values
+            .iter()
+            .flatten()
+            .enumerate()
+            .map(|(index, value)| {
+                let number = field_value(&ctx.index("enum", index).key("value"), value, width)?;
+                Ok((value.name.to_uppercase(), value.desc.clone(), number))
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
+
+        for copy in 0..copies {
+            map.push(MapField {
+                name: match first_copy {
+                    Some(first) => format!("{}_{}", name, first + copy),
+                    None => name.clone(),
+                },
+                desc: f_desc.desc.clone(),
+                start: start + copy * width,
+                width,
+                swaccess: f_desc.swaccess.clone(),
+                values: values.clone(),
+            });
+        }
+    }
+    Ok(map)
+}
+
+/// Arguments of the `#[registers]` attribute
+///
+/// The source of the IP description is followed by optional overrides of its parameters, eg.
+/// `#[registers("hw/ip/rv_plic/data/rv_plic.hjson", params(NumSrc = 185, NumTargets = 1))]`.
+pub(crate) struct RegistersArgs {
+    pub(crate) source: IpSource,
+    pub(crate) params: Vec<(Ident, Expr)>,
+}
+
+/// Source of the IP description of the `#[registers]` attribute
+pub(crate) enum IpSource {
+    /// Path relative to the OpenTitan repository, eg. `"hw/ip/aes/data/aes.hjson"`
+    OpenTitan(LitStr),
+    /// Path relative to the crate using the attribute, eg. `crate = "hw/accel.hjson"`
+    Crate(LitStr),
+    /// Description written into the attribute, eg. `hjson = r#"{ ... }"#`
+    Inline(LitStr),
+}
+
+impl IpSource {
+    /// Reads & deserializes the IP description
+    fn read<T: DeserializeOwned>(&self, opentitan_path: &Path) -> syn::Result<T> {
+        match self {
+            IpSource::OpenTitan(path) => hjson::from_file(&opentitan_path.join(path.value()))
+                .map_err(|error| parse::Error::new(path.span(), error)),
+            IpSource::Crate(path) => hjson::from_file(&crate_path(path)?)
+                .map_err(|error| parse::Error::new(path.span(), error)),
+            IpSource::Inline(hjson) => hjson::from_str(&hjson.value())
+                .map_err(|error| parse::Error::new(hjson.span(), format!("<inline>:{}", error))),
+        }
+    }
+
+    /// Makes cargo rebuild the crate when its own IP description changes, which proc macros
+    /// can't declare otherwise
+    fn track(&self) -> syn::Result<proc_macro2::TokenStream> {
+        Ok(match self {
+            IpSource::Crate(path) => {
+                let file = crate_path(path)?.display().to_string();
+                quote!(
+                    const _: &[u8] = include_bytes!(#file);
+                )
+            }
+            IpSource::OpenTitan(_) | IpSource::Inline(_) => quote!(),
+        })
+    }
+
+    /// Name of the description in error messages, its path or `<inline>`
+    fn name(&self) -> String {
+        match self {
+            IpSource::OpenTitan(path) | IpSource::Crate(path) => path.value(),
+            IpSource::Inline(_) => "<inline>".to_owned(),
+        }
+    }
+
+    fn span(&self) -> Span {
+        match self {
+            IpSource::OpenTitan(lit) | IpSource::Crate(lit) | IpSource::Inline(lit) => lit.span(),
+        }
+    }
+}
+
+/// Returns the absolute path of the crate relative path `path`
+fn crate_path(path: &LitStr) -> syn::Result<PathBuf> {
+    let manifest_dir = env::var_os("CARGO_MANIFEST_DIR").ok_or_else(|| {
+        parse::Error::new(
+            path.span(),
+            "CARGO_MANIFEST_DIR is not set, crate paths need a build with cargo",
+        )
+    })?;
+    Ok(Path::new(&manifest_dir).join(path.value()))
+}
+
+impl Parse for RegistersArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let source = if input.peek(LitStr) {
+            IpSource::OpenTitan(input.parse()?)
+        } else if input.peek(Token![crate]) {
+            input.parse::<Token![crate]>()?;
+            input.parse::<Token![=]>()?;
+            IpSource::Crate(input.parse()?)
+        } else {
+            let keyword = input
+                .parse::<Ident>()
+                .ok()
+                .filter(|keyword| keyword == "hjson");
+            if keyword.is_none() {
+                return Err(input.error(
+                    "expected the path of the IP description, `crate = \"...\"` or `hjson = \"...\"`",
+                ));
+            }
+            input.parse::<Token![=]>()?;
+            IpSource::Inline(input.parse()?)
+        };
+        let mut params = vec![];
+        if !input.is_empty() {
+            input.parse::<Token![,]>()?;
+        }
+        if !input.is_empty() {
+            let keyword = input.parse::<Ident>()?;
+            if keyword != "params" {
+                return Err(syn::Error::new(keyword.span(), "expected `params(...)`"));
+            }
+            let content;
+            parenthesized!(content in input);
+            while !content.is_empty() {
+                let name = content.parse::<Ident>()?;
+                content.parse::<Token![=]>()?;
+                params.push((name, content.parse::<Expr>()?));
+                if !content.is_empty() {
+                    content.parse::<Token![,]>()?;
+                }
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(RegistersArgs { source, params })
+    }
+}
+
+/// Replaces the defaults of the overridden parameters by the values given in the attribute
+///
+/// Values are evaluated against the original defaults, so they can refer to other parameters.
+fn override_params(
+    ip_name: &str,
+    param_list: &mut Option<Vec<ParamDescription>>,
+    overrides: Vec<(Ident, Expr)>,
+) -> syn::Result<()> {
+    let mut values = vec![];
+    for (name, value) in overrides {
+        let Some(index) = param_list
+            .iter()
+            .flatten()
+            .position(|param| name == param.name)
+        else {
+            return Err(syn::Error::new(
+                name.span(),
+                format!("{} has no parameter {}", ip_name, name),
+            ));
+        };
+        if values.iter().any(|(other, _)| *other == index) {
+            return Err(syn::Error::new(
+                name.span(),
+                format!("parameter {} is overridden twice", name),
+            ));
+        }
+        let expr = quote!(#value).to_string();
+        let value = eval_param_expr(&expr, param_list, 0)
+            .map_err(|error| syn::Error::new_spanned(&value, error))?;
+        values.push((index, value));
+    }
+
+    for (index, value) in values {
+        if let Some(params) = param_list {
+            params[index].default = Some(value.to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Generates the register block & everything that belongs to it for an IP description
+fn gen_registers(
+    ctx: &Context,
+    ip: IPDescription,
+    input_struct: ItemStruct,
+) -> syn::Result<proc_macro2::TokenStream> {
+    // Static info for generation
+    let struct_ident = input_struct.ident;
+    let struct_vis = input_struct.vis;
+
+    // Generate Register descriptions for tock-registers crate
+    let has_interrupts = ip.interrupt_list.is_some();
+    let mut register_counter = 0;
+    let has_alerts = !ip.alert_list.is_empty();
+    let mut registers = vec![gen_interrupt_alert_registers(
+        ctx,
+        &mut register_counter,
+        has_interrupts,
+        has_alerts,
+    )?];
+    for (index, reg_desc) in ip.registers.iter().enumerate() {
+        registers.push(gen_register(
+            &ctx.register(index, reg_desc),
+            &mut register_counter,
+            reg_desc,
+            &ip.param_list,
+        )?);
+    }
+    registers.push(quote!( (#register_counter => @END), ));
+
+    // Generate Bitfield descriptions for tock-registers crate & the names used for tracing
+    let mut bitfields = vec![];
+    let mut infos = vec![];
+    let mut interrupts = None;
+    if let Some(intr_list) = &ip.interrupt_list {
+        let bits = interrupt_bits(ctx, intr_list)?;
+        let (bitfield, info) =
+            gen_interrupt_alert_bitfields(format_ident!("intr"), intr_list, &bits);
+        bitfields.push(bitfield);
+        infos.push(info);
+        interrupts = Some(gen_interrupts(ctx, &struct_ident, intr_list, &bits)?);
+    }
+    let mut alerts = None;
+    if has_alerts {
+        let alert_bits = (0..ip.alert_list.len())
+            .map(|bit| (bit, 1))
+            .collect::<Vec<_>>();
+        let (bitfield, info) =
+            gen_interrupt_alert_bitfields(format_ident!("alert"), &ip.alert_list, &alert_bits);
+        bitfields.push(bitfield);
+        infos.push(info);
+        alerts = Some(gen_alerts(ctx, &struct_ident, &ip.alert_list)?);
+    }
+    for (index, reg_desc) in ip.registers.iter().enumerate() {
+        if let Some((bitfield, info)) =
+            gen_bitfield(&ctx.register(index, reg_desc), reg_desc, &ip.param_list)?
+        {
+            bitfields.push(bitfield);
+            infos.push(info);
+        }
+    }
+
+    // Generate index based field accessors for multiregs
+    let mut accessors = vec![];
+    for (index, reg_desc) in ip.registers.iter().enumerate() {
+        if let Some(multireg) = &reg_desc.multireg {
+            accessors.push(gen_multireg_accessors(
+                &ctx.register(index, reg_desc),
+                multireg,
+                &ip.param_list,
+            )?);
+        }
+    }
+
+    // Generate rust enums for enumerated fields
+    let mut field_enums = vec![];
+    for (index, reg_desc) in ip.registers.iter().enumerate() {
+        field_enums.extend(gen_field_enums(
+            &ctx.register(index, reg_desc),
+            reg_desc,
+            &ip.param_list,
+        )?);
+    }
+
+    // Generate the update error information of shadowed registers
+    let shadowed = gen_shadowed_impls(ctx, &struct_ident, &ip.registers)?;
+
+    // Generate the links between write protected registers & their regwen
+    let regwen = gen_regwen_impls(ctx, &struct_ident, &ip.registers, &ip.param_list)?;
+
+    // Generate reset values & the check against them
+    let reset_check = gen_reset_check(ctx, has_interrupts, &ip.registers, &ip.param_list)?;
+
+    // Generate the hardware parameters
+    let params = gen_params(&ip.param_list);
+
+    Ok(quote!(
+        opentitan_lib::tock_registers::register_structs! {
+            #struct_vis #struct_ident {
+                #( #registers )*
+            }
+        }
+
+        opentitan_lib::tock_registers::register_bitfields! [
+            u32,
+            #( #bitfields ),*
+        ];
+
+        #( #infos )*
+
+        #( #field_enums )*
+
+        #interrupts
+
+        #alerts
+
+        #( #shadowed )*
+
+        #( #regwen )*
+
+        impl #struct_ident {
+            #( #accessors )*
+
+            #reset_check
+        }
+
+        #params
+    ))
+}
+
+/// Location inside the IP description that errors are reported for
+///
+/// Errors are reported on the `#[registers(...)]` attribute and name the hjson file, the key path
+/// inside it (eg. `registers[4].fields[2].bits`) and the register & field they belong to.
+#[derive(Clone)]
+struct Context {
+    span: Span,
+    file: PathBuf,
+    path: String,
+    /// Key of the register list, which is nested for IPs with several interfaces
+    registers: String,
+    register: Option<String>,
+    field: Option<String>,
+}
+
+impl Context {
+    fn new(span: Span, file: &Path) -> Self {
+        Context {
+            span,
+            file: file.to_owned(),
+            path: String::new(),
+            registers: "registers".to_owned(),
+            register: None,
+            field: None,
+        }
+    }
+
+    /// Uses the register list of the interface `name`
+    fn interface(&self, name: &str) -> Self {
+        let mut ctx = self.clone();
+        ctx.registers = format!("registers.{}", name);
+        ctx
+    }
+
+    /// Descends into the value of `key`
+    fn key(&self, key: &str) -> Self {
+        let mut ctx = self.clone();
+        if !ctx.path.is_empty() {
+            ctx.path.push('.');
+        }
+        ctx.path.push_str(key);
+        ctx
+    }
+
+    /// Descends into the element `index` of the array at `key`
+    fn index(&self, key: &str, index: usize) -> Self {
+        let mut ctx = self.key(key);
+        ctx.path.push_str(&format!("[{}]", index));
+        ctx
+    }
+
+    /// Descends into the register description `index` of the register list
+    fn register(&self, index: usize, reg_desc: &RegisterDescription) -> Self {
+        let ctx = self.index(&self.registers, index);
+        let (mut ctx, name) = match reg_desc {
+            RegisterDescription {
+                multireg: Some(multireg),
+                ..
+            } => (ctx.key("multireg"), Some(&multireg.name)),
+            RegisterDescription {
+                window: Some(window),
+                ..
+            } => (ctx.key("window"), Some(&window.name)),
+            RegisterDescription { name, .. } => (ctx, name.as_ref()),
+        };
+        ctx.register = name.cloned();
+        ctx
+    }
+
+    /// Descends into the field description `index` of the `fields` list of a register
+    fn field(&self, index: usize, f_desc: &FieldDescription) -> Self {
+        let mut ctx = self.index("fields", index);
+        ctx.field = f_desc.name.clone();
+        ctx
+    }
+
+    /// Creates an error on the `#[registers(...)]` attribute describing this location
+    fn error(&self, message: impl Display) -> syn::Error {
+        let mut location = format!("{}", self.file.display());
+        if !self.path.is_empty() {
+            location.push_str(&format!(": {}", self.path));
+        }
+        match (&self.register, &self.field) {
+            (Some(register), Some(field)) => {
+                location.push_str(&format!(" (register {}, field {})", register, field))
+            }
+            (Some(register), None) => location.push_str(&format!(" (register {})", register)),
+            (None, Some(field)) => location.push_str(&format!(" (field {})", field)),
+            (None, None) => {}
+        }
+        syn::Error::new(self.span, format!("{}: {}", location, message))
+    }
+}
+
+/// Generates the interrupt & alert register descriptions
+///
+/// Like reggen, the alert test register is left out for IPs without alerts.
+fn gen_interrupt_alert_registers(
+    ctx: &Context,
+    register_counter: &mut usize,
+    gen_intr: bool,
+    gen_alert: bool,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let mut docs = vec![];
+    let mut offsets = vec![];
+    let mut names = vec![];
+    let mut reg_types = vec![];
+    let mut types = vec![];
+    if gen_intr {
+        docs.extend(vec![
+            "([`intr`]) Interrupt State register",
+            "([`intr`]) Interrupt Enable register",
+            "([`intr`]) Interrupt Test register",
+        ]);
+        offsets.extend(vec![
+            *register_counter,
+            *register_counter + 4,
+            *register_counter + 8,
+        ]);
+        names.extend(vec![
+            format_ident!("intr_state"),
+            format_ident!("intr_enable"),
+            format_ident!("intr_test"),
+        ]);
+        reg_types.extend(vec![
+            register_type(ctx, "rw1c", false)?,
+            register_type(ctx, "rw", false)?,
+            register_type(ctx, "wo", false)?,
+        ]);
+        types.extend(vec![
+            format_ident!("intr"),
+            format_ident!("intr"),
+            format_ident!("intr"),
+        ]);
+        *register_counter += 12;
+    }
+    if gen_alert {
+        docs.push("([`alert`]) Alert Test register");
+        offsets.push(*register_counter);
+        *register_counter += 4;
+        names.push(format_ident!("alert_test"));
+        reg_types.push(register_type(ctx, "wo", false)?);
+        types.push(format_ident!("alert"));
+    }
+
+    Ok(quote!(
+        #(
+            #[doc = #docs]
+            (#offsets => pub #names: #reg_types<u32, #types::Register>),
+        )*
+    ))
+}
+
+/// Generates bitfield descriptions for interrupts or alerts & their register information
+///
+/// `bits` are the first bit & the width of every signal of `list`.
+fn gen_interrupt_alert_bitfields(
+    name: Ident,
+    list: &[InterruptDescription],
+    bits: &[(usize, usize)],
+) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    // Compute names & offsets
+    let names = list
+        .iter()
+        .map(|x| format_ident!("{}", x.name.to_lowercase()))
+        .collect::<Vec<_>>();
+    let offsets = bits.iter().map(|(start, _)| *start).collect::<Vec<_>>();
+    let widths = bits.iter().map(|(_, width)| *width).collect::<Vec<_>>();
+    let docs = list.iter().map(|x| x.desc.clone());
+    let info = gen_register_info(&name, &names, &offsets, &widths);
+
+    let bitfield = quote!(
+        /// All the submodules/constants represent parts of the content of this register
+        pub #name [
+            #(
+                #[doc = #docs]
+                #names OFFSET(#offsets) NUMBITS(#widths) []
+            ),*
+        ]
+    );
+    (bitfield, info)
+}
+
+/// Returns the first bit & the width of every interrupt of `list`, interrupts can be wider than
+/// one bit
+fn interrupt_bits(
+    ctx: &Context,
+    list: &[InterruptDescription],
+) -> syn::Result<Vec<(usize, usize)>> {
+    let mut bits = vec![];
+    let mut start = 0;
+    for (index, interrupt) in list.iter().enumerate() {
+        let ctx = ctx.index("interrupt_list", index);
+        let width = match &interrupt.width {
+            Some(width) => parse_int(width)
+                .and_then(|width| usize::try_from(width).ok())
+                .filter(|width| *width > 0)
+                .ok_or_else(|| {
+                    ctx.key("width")
+                        .error(format!("invalid width \"{}\"", width))
+                })?,
+            None => 1,
+        };
+        if start + width > 32 {
+            return Err(ctx.error("the interrupts do not fit into 32 bits"));
+        }
+        bits.push((start, width));
+        start += width;
+    }
+    Ok(bits)
+}
+
+/// Generates the `Interrupt` enum of the bits of the interrupt registers & the
+/// `opentitan_lib::registers::Interrupts` implementation of the register block
+///
+/// Interrupts wider than one bit get a variant per bit, eg. `Gpio0` to `Gpio31`.
+fn gen_interrupts(
+    ctx: &Context,
+    struct_ident: &Ident,
+    list: &[InterruptDescription],
+    bits: &[(usize, usize)],
+) -> syn::Result<proc_macro2::TokenStream> {
+    let mut variants: Vec<Ident> = vec![];
+    let mut numbers = vec![];
+    let mut names = vec![];
+    let mut docs = vec![];
+    let mut status = vec![];
+    for (index, (interrupt, (start, width))) in list.iter().zip(bits).enumerate() {
+        let ctx = ctx.index("interrupt_list", index);
+        let is_status = match interrupt.ty.as_deref() {
+            None | Some("event") => false,
+            Some("status") => true,
+            Some(ty) => {
+                return Err(ctx
+                    .key("type")
+                    .error(format!("unknown interrupt type \"{}\"", ty)))
+            }
+        };
+        for bit in 0..*width {
+            let name = if *width > 1 {
+                format!("{}_{}", interrupt.name, bit)
+            } else {
+                interrupt.name.clone()
+            };
+            let variant = format_ident!("{}", camel_case(&name));
+            if variants.contains(&variant) {
+                return Err(ctx
+                    .key("name")
+                    .error(format!("duplicate interrupt name {}", interrupt.name)));
+            }
+            variants.push(variant);
+            numbers.push((start + bit) as u32);
+            names.push(name);
+            docs.push(interrupt.desc.clone());
+            status.push(is_status);
+        }
+    }
+    let reg_types = [
+        register_type(ctx, "rw1c", false)?,
+        register_type(ctx, "rw", false)?,
+        register_type(ctx, "wo", false)?,
+    ];
+    let [state, enable, test] = &reg_types;
+
+    Ok(quote!(
+        /// Interrupts of the IP, the bits of the [`intr`] registers
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #[repr(u32)]
+        pub enum Interrupt {
+            #(
+                #[doc = #docs]
+                #variants = #numbers,
+            )*
+        }
+
+        impl Interrupt {
+            /// All interrupts of the IP
+            pub const ALL: &'static [Interrupt] = &[#(Interrupt::#variants),*];
+
+            /// Returns the name of the interrupt in the IP description
+            pub const fn name(self) -> &'static str {
+                match self {
+                    #(Interrupt::#variants => #names,)*
+                }
+            }
+
+            /// Whether the interrupt is a status interrupt, which stays pending while its cause
+            /// persists & can't be acknowledged
+            pub const fn is_status(self) -> bool {
+                match self {
+                    #(Interrupt::#variants => #status,)*
+                }
+            }
+        }
+
+        impl From<Interrupt> for u32 {
+            fn from(value: Interrupt) -> u32 {
+                match value {
+                    #(Interrupt::#variants => #numbers,)*
+                }
+            }
+        }
+
+        impl TryFrom<u32> for Interrupt {
+            type Error = opentitan_lib::Error;
+
+            fn try_from(value: u32) -> Result<Self, opentitan_lib::Error> {
+                match value {
+                    #(#numbers => Ok(Interrupt::#variants),)*
+                    _ => Err(opentitan_lib::Error::InvalidArgument),
+                }
+            }
+        }
+
+        impl opentitan_lib::registers::Interrupts for #struct_ident {
+            type Interrupt = Interrupt;
+            type Register = intr::Register;
+
+            fn is_status(interrupt: Interrupt) -> bool {
+                interrupt.is_status()
+            }
+
+            fn intr_state(&self) -> &#state<u32, intr::Register> {
+                &self.intr_state
+            }
+
+            fn intr_enable(&self) -> &#enable<u32, intr::Register> {
+                &self.intr_enable
+            }
+
+            fn intr_test(&self) -> &#test<u32, intr::Register> {
+                &self.intr_test
+            }
+        }
+    ))
+}
+
+/// Generates the `Alert` enum of the bits of the alert test register & the method raising them
+fn gen_alerts(
+    ctx: &Context,
+    struct_ident: &Ident,
+    alert_list: &[InterruptDescription],
+) -> syn::Result<proc_macro2::TokenStream> {
+    let mut variants: Vec<Ident> = vec![];
+    for (index, alert) in alert_list.iter().enumerate() {
+        let variant = format_ident!("{}", camel_case(&alert.name));
+        if variants.contains(&variant) {
+            return Err(ctx
+                .index("alert_list", index)
+                .key("name")
+                .error(format!("duplicate alert name {}", alert.name)));
+        }
+        variants.push(variant);
+    }
+    let numbers = (0..alert_list.len() as u32).collect::<Vec<_>>();
+    let docs = alert_list.iter().map(|alert| alert.desc.clone());
+    let names = alert_list.iter().map(|alert| alert.name.clone());
+    let classes = alert_list.iter().map(|alert| {
+        if is_fatal_alert(&alert.name) {
+            quote!(Fatal)
+        } else {
+            quote!(Recoverable)
+        }
+    });
+
+    Ok(quote!(
+        /// Alerts of the IP, the bits of the [`alert`] test register
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #[repr(u32)]
+        pub enum Alert {
+            #(
+                #[doc = #docs]
+                #variants = #numbers,
+            )*
+        }
+
+        impl Alert {
+            /// All alerts of the IP
+            pub const ALL: &'static [Alert] = &[#(Alert::#variants),*];
+
+            /// Returns the name of the alert in the IP description
+            pub const fn name(self) -> &'static str {
+                match self {
+                    #(Alert::#variants => #names,)*
+                }
+            }
+
+            /// Returns whether the alert is fatal or recoverable, taken from its name
+            pub const fn class(self) -> opentitan_lib::devices::alerts::AlertClass {
+                match self {
+                    #(Alert::#variants => opentitan_lib::devices::alerts::AlertClass::#classes,)*
+                }
+            }
+        }
+
+        impl From<Alert> for u32 {
+            fn from(value: Alert) -> u32 {
+                match value {
+                    #(Alert::#variants => #numbers,)*
+                }
+            }
+        }
+
+        impl TryFrom<u32> for Alert {
+            type Error = opentitan_lib::Error;
+
+            fn try_from(value: u32) -> Result<Self, opentitan_lib::Error> {
+                match value {
+                    #(#numbers => Ok(Alert::#variants),)*
+                    _ => Err(opentitan_lib::Error::InvalidArgument),
+                }
+            }
+        }
+
+        impl #struct_ident {
+            /// Raises `alert` once through the alert test register
+            pub fn trigger_test(&self, alert: Alert) {
+                opentitan_lib::tock_registers::interfaces::Writeable::set(
+                    &self.alert_test,
+                    1 << u32::from(alert),
+                );
+            }
+        }
+    ))
+}
+
+/// Generates the `opentitan_lib::registers::RegisterInfo` implementation of the bitfield `name`
+fn gen_register_info(
+    name: &Ident,
+    fields: &[Ident],
+    offsets: &[usize],
+    numbits: &[usize],
+) -> proc_macro2::TokenStream {
+    let reg_name = name.to_string();
+    let field_names = fields.iter().map(|field| field.to_string());
+    quote!(
+        impl opentitan_lib::registers::RegisterInfo for #name::Register {
+            const NAME: &'static str = #reg_name;
+            const FIELDS: &'static [(&'static str, usize, usize)] = &[
+                #( (#field_names, #offsets, #numbits) ),*
+            ];
+        }
+    )
+}
+
+/// Generates standard register descriptions
+fn gen_register(
+    ctx: &Context,
+    register_counter: &mut usize,
+    register_description: &RegisterDescription,
+    params: &Option<Vec<ParamDescription>>,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let address = *register_counter;
+
+    let (str_name, swaccess, desc, fields, items, shadowed) = match register_description {
+        RegisterDescription {
+            name: Some(name),
+            swaccess: Some(swaccess),
+            desc,
+            fields,
+            ..
+        } => (
+            name,
+            swaccess,
+            desc,
+            fields,
+            None,
+            register_description.is_shadowed(),
+        ),
+        RegisterDescription {
+            window: Some(window),
+            ..
+        } => return gen_window(ctx, register_counter, window, params),
+        RegisterDescription {
+            multireg: Some(multireg),
+            ..
+        } => (
+            &multireg.name,
+            &multireg.swaccess,
+            &multireg.desc,
+            &multireg.fields,
+            MultiregLayout::new(ctx, multireg, params)?.array_len(),
+            multireg.is_shadowed(),
+        ),
+        RegisterDescription {
+            skipto: Some(skipto),
+            ..
+        } => {
+            let ctx = ctx.key("skipto");
+            let Some(val) = parse_int(skipto) else {
+                return Err(ctx.error(format!("invalid offset \"{}\"", skipto)));
+            };
+            let val = val as usize;
+            if val < address || !val.is_multiple_of(4) {
+                return Err(ctx.error(format!(
+                    "offset {:#x} is unaligned or overlaps the registers before it, which end at {:#x}",
+                    val, address
+                )));
+            }
+            *register_counter = val;
+            let name = format_ident!("_ignored{}", address);
+            return Ok(quote!(
+                (#address => #name),
+            ));
+        }
+        RegisterDescription { name: Some(_), .. } => {
+            return Err(ctx.key("swaccess").error("register has no swaccess"));
+        }
+        _ => {
+            return Err(ctx.error(
+                "unknown register description, expected a register, multireg, window or skipto",
+            ));
+        }
+    };
+
+    let name = format_ident!("{}", str_name.to_lowercase());
+    let reg_type = register_type(&ctx.key("swaccess"), swaccess, shadowed)?;
+
+    // See `gen_regwen_impls`, the copies of these multiregs are written through the register block
+    let regwen_note = match register_description {
+        RegisterDescription {
+            multireg:
+                Some(MultiregDescription {
+                    regwen: Some(regwen),
+                    regwen_multi,
+                    ..
+                }),
+            ..
+        } if items.is_some() && !regwen_multi.as_deref().is_some_and(is_true) => {
+            let name = str_name.to_lowercase();
+            format!(
+                "\n\nThe copies are write protected by `{}`, [`try_write_{}`](Self::try_write_{}) \
+                 & [`try_set_{}`](Self::try_set_{}) fail instead of writing to locked copies.",
+                regwen.to_lowercase(),
+                name,
+                name,
+                name,
+                name
+            )
+        }
+        _ => String::new(),
+    };
+
+    Ok(if fields.is_none() {
+        let doc = &desc
+            .clone()
+            .unwrap_or_else(|| "<no description>".to_owned());
+        if let Some(item_num) = items {
+            *register_counter += 4 * item_num;
+            quote!(
+                #[doc = #doc]
+                (#address => pub #name: [#reg_type<u32>; #item_num]),
+            )
+        } else {
+            *register_counter += 4;
+            quote!(
+                #[doc = #doc]
+                (#address => pub #name: #reg_type<u32>),
+            )
+        }
+    } else {
+        let doc = format!(
+            "([`{}`]) {}{}",
+            str_name.to_lowercase(),
+            desc.clone()
+                .unwrap_or_else(|| "<no description>".to_owned()),
+            regwen_note
+        );
+        if let Some(item_num) = items {
+            *register_counter += 4 * item_num;
+            quote!(
+                #[doc = #doc]
+                (#address => pub #name: [#reg_type<u32, #name::Register>; #item_num]),
+            )
+        } else {
+            *register_counter += 4;
+            quote!(
+                #[doc = #doc]
+                (#address => pub #name: #reg_type<u32, #name::Register>),
+            )
+        }
+    })
+}
+
+/// Generates the description of a memory window
+fn gen_window(
+    ctx: &Context,
+    register_counter: &mut usize,
+    window: &WindowDescription,
+    params: &Option<Vec<ParamDescription>>,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let address = *register_counter;
+    let name = format_ident!("{}", window.name.to_lowercase());
+    let doc = window
+        .desc
+        .clone()
+        .unwrap_or_else(|| "<no description>".to_owned());
+
+    let words = parse_count(&ctx.key("items"), &window.items, params)?;
+    if words == 0 {
+        return Err(ctx.key("items").error("window has no items"));
+    }
+    let byte_write = window.byte_write.as_deref().is_some_and(is_true);
+    let valid_bits = match &window.validbits {
+        Some(validbits) => match parse_count(&ctx.key("validbits"), validbits, params)? {
+            valid_bits @ 1..=32 => valid_bits as u32,
+            _ => {
+                return Err(ctx
+                    .key("validbits")
+                    .error(format!("invalid number of valid bits \"{}\"", validbits)))
+            }
+        },
+        None => 32,
+    };
+    let access = match window.swaccess.as_str() {
+        "ro" => quote!(opentitan_lib::registers::ReadOnlyAccess),
+        "wo" => quote!(opentitan_lib::registers::WriteOnlyAccess),
+        "rw" => quote!(opentitan_lib::registers::ReadWriteAccess),
+        x => {
+            return Err(ctx
+                .key("swaccess")
+                .error(format!("unsupported window swaccess \"{}\"", x)))
+        }
+    };
+
+    *register_counter += 4 * words;
+    Ok(quote!(
+        #[doc = #doc]
+        (#address => pub #name: opentitan_lib::registers::Window<
+            #access, #words, #byte_write, #valid_bits
+        >),
+    ))
+}
+
+/// Generates optional standard register bitfields & their register information
+fn gen_bitfield(
+    ctx: &Context,
+    register_description: &RegisterDescription,
+    params: &Option<Vec<ParamDescription>>,
+) -> syn::Result<Option<(proc_macro2::TokenStream, proc_macro2::TokenStream)>> {
+    let (reg_name, fields, layout) = match register_description {
+        RegisterDescription {
+            name: Some(name),
+            fields: Some(fields),
+            ..
+        } => (name, fields, None),
+        RegisterDescription {
+            multireg:
+                Some(
+                    multireg @ MultiregDescription {
+                        name,
+                        fields: Some(fields),
+                        ..
+                    },
+                ),
+            ..
+        } => (
+            name,
+            fields,
+            Some(MultiregLayout::new(ctx, multireg, params)?),
+        ),
+        _ => {
+            return Ok(None);
+        }
+    };
+
+    // Compacted multiregs place several copies of their single field in one register
+    let copies = match layout {
+        Some(layout) if layout.compact => layout.count.min(layout.per_reg),
+        _ => 1,
+    };
+
+    let reg_name = format_ident!("{}", reg_name.to_lowercase());
+    let mut names = vec![];
+    let mut offsets = vec![];
+    let mut numbits = vec![];
+    let mut docs = vec![];
+    let mut field_vals = vec![];
+    for (index, f_desc) in fields.iter().enumerate() {
+        let ctx = ctx.field(index, f_desc);
+        let (start, width) = bit_range(&ctx.key("bits"), &f_desc.bits)?;
+        let name = f_desc.name.clone().unwrap_or_else(|| "data".to_owned());
+        let desc = f_desc
+            .desc
+            .clone()
+            .unwrap_or_else(|| "<no documentation>".to_owned());
+        let vals = if let Some(vals) = f_desc.values.borrow() {
+            gen_field_values(&ctx, vals, width)?
+        } else {
+            quote!()
+        };
+
+        for copy in 0..copies {
+            if copies > 1 {
+                names.push(format_ident!("{}_{}", name.to_lowercase(), copy));
+                offsets.push(start + copy * width);
+                docs.push(format!("{} (copy {})", desc, copy));
+            } else {
+                names.push(format_ident!("{}", name.to_lowercase()));
+                offsets.push(start);
+                docs.push(desc.clone());
+            }
+            numbits.push(width);
+            field_vals.push(vals.clone());
+        }
+    }
+
+    if names.is_empty() {
+        Ok(None)
+    } else {
+        let info = gen_register_info(&reg_name, &names, &offsets, &numbits);
+        Ok(Some((
+            quote!(
+                /// All the submodules/constants represent parts of the content of this register
+                pub #reg_name [
+                    #(
+                        #[doc = #docs]
+                        #names OFFSET(#offsets) NUMBITS(#numbits) [ #field_vals ]
+                    ),*
+                ]
+            ),
+            info,
+        )))
+    }
+}
+
+/// Generates the accessors that map the index of a multireg field copy to its register & field
+fn gen_multireg_accessors(
+    ctx: &Context,
+    multireg: &MultiregDescription,
+    params: &Option<Vec<ParamDescription>>,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let Some(fields) = &multireg.fields else {
+        return Ok(quote!());
+    };
+    let layout = MultiregLayout::new(ctx, multireg, params)?;
+
+    let reg_name = format_ident!("{}", multireg.name.to_lowercase());
+    let reg_type = register_type(
+        &ctx.key("swaccess"),
+        &multireg.swaccess,
+        multireg.is_shadowed(),
+    )?;
+    let count = layout.count;
+    let count_name = format_ident!("{}_COUNT", multireg.name.to_uppercase());
+    let cname = multireg.cname.as_ref().unwrap_or(&multireg.name);
+    let count_doc = format!(
+        "Number of {} instances in [`{}`](Self::{})",
+        cname, reg_name, reg_name
+    );
+
+    let per_reg = layout.per_reg;
+    let register = if layout.array_len().is_none() {
+        quote!(&self.#reg_name)
+    } else if per_reg == 1 {
+        quote!(&self.#reg_name[index])
+    } else {
+        quote!(&self.#reg_name[index / #per_reg])
+    };
+
+    let mut accessors = vec![];
+    for (index, f_desc) in fields.iter().enumerate() {
+        let field_name = f_desc.name.clone().unwrap_or_else(|| "data".to_owned());
+        let fn_name = format_ident!(
+            "{}_{}",
+            multireg.name.to_lowercase(),
+            field_name.to_lowercase()
+        );
+        let (start, width) = bit_range(&ctx.field(index, f_desc).key("bits"), &f_desc.bits)?;
+        let mask = if width >= 32 {
+            u32::MAX
+        } else {
+            (1u32 << width) - 1
+        };
+        let shift = if layout.compact && per_reg > 1 {
+            quote!(#start + (index % #per_reg) * #width)
+        } else {
+            quote!(#start)
+        };
+        let doc = format!(
+            "Returns the register & `{}` field of {} instance `index`",
+            field_name.to_lowercase(),
+            cname
+        );
+        accessors.push(quote!(
+            #[doc = #doc]
+            ///
+            /// # Panics
+            /// If `index` is out of range
+            pub fn #fn_name(
+                &self,
+                index: usize,
+            ) -> (
+                &#reg_type<u32, #reg_name::Register>,
+                opentitan_lib::tock_registers::fields::Field<u32, #reg_name::Register>,
+            ) {
+                assert!(index < Self::#count_name, "multireg index out of range");
+                (
+                    #register,
+                    opentitan_lib::tock_registers::fields::Field::<u32, #reg_name::Register>::new(#mask, #shift),
+                )
+            }
+        ));
+    }
+
+    Ok(quote!(
+        #[doc = #count_doc]
+        pub const #count_name: usize = #count;
+
+        #( #accessors )*
+    ))
+}
+
+/// Generates the `opentitan_lib::registers::ShadowedRegister` implementations of all shadowed
+/// registers
+///
+/// The update error bit is the status field named after the update error alert of the register.
+/// Shadowed multiregs with an update error alert are rejected, since the offset to the status
+/// register differs per copy.
+fn gen_shadowed_impls(
+    ctx: &Context,
+    struct_ident: &Ident,
+    registers: &[RegisterDescription],
+) -> syn::Result<Vec<proc_macro2::TokenStream>> {
+    let mut impls = vec![];
+    for (index, reg_desc) in registers.iter().enumerate() {
+        let reg_ctx = ctx.register(index, reg_desc);
+        let (name, update_err_alert) = match reg_desc {
+            RegisterDescription {
+                name: Some(name),
+                fields: Some(_),
+                update_err_alert,
+                ..
+            } if reg_desc.is_shadowed() => (name, update_err_alert),
+            RegisterDescription {
+                multireg:
+                    Some(
+                        multireg @ MultiregDescription {
+                            name,
+                            fields: Some(_),
+                            update_err_alert,
+                            ..
+                        },
+                    ),
+                ..
+            } if multireg.is_shadowed() => {
+                if update_err_alert.is_some() {
+                    return Err(reg_ctx
+                        .key("update_err_alert")
+                        .error("update errors of shadowed multiregs are not supported"));
+                }
+                (name, update_err_alert)
+            }
+            _ => continue,
+        };
+        let reg_name = format_ident!("{}", name.to_lowercase());
+
+        // Without an update error alert the IP has no status bit to check
+        let update_error = match update_err_alert {
+            Some(alert) => {
+                let Some((status_reg, mask)) = find_status_field(ctx, registers, alert)? else {
+                    return Err(reg_ctx.key("update_err_alert").error(format!(
+                        "no status field reports the update error alert {}",
+                        alert
+                    )));
+                };
+                let status_reg = format_ident!("{}", status_reg.to_lowercase());
+                quote!(Some((
+                    core::mem::offset_of!(#struct_ident, #status_reg) as isize
+                        - core::mem::offset_of!(#struct_ident, #reg_name) as isize,
+                    #mask,
+                )))
+            }
+            None => quote!(None),
+        };
+
+        impls.push(quote!(
+            impl opentitan_lib::registers::ShadowedRegister for #reg_name::Register {
+                const UPDATE_ERROR: Option<(isize, u32)> = #update_error;
+            }
+        ));
+    }
+    Ok(impls)
+}
+
+/// Finds the status field reporting the given alert, returns its register name & mask
+fn find_status_field<'a>(
+    ctx: &Context,
+    registers: &'a [RegisterDescription],
+    alert: &str,
+) -> syn::Result<Option<(&'a str, u32)>> {
+    let alert_field = format!("alert_{}", alert);
+    for (index, reg_desc) in registers.iter().enumerate() {
+        let (Some(name), Some(fields)) = (&reg_desc.name, &reg_desc.fields) else {
+            continue;
+        };
+        let field = fields.iter().enumerate().find(|(_, f_desc)| {
+            f_desc.name.as_ref().is_some_and(|f_name| {
+                f_name.eq_ignore_ascii_case(&alert_field) || f_name.eq_ignore_ascii_case(alert)
+            })
+        });
+        if let Some((f_index, f_desc)) = field {
+            let ctx = ctx.register(index, reg_desc).field(f_index, f_desc);
+            return Ok(Some((
+                name.as_str(),
+                bits_mask(&ctx.key("bits"), &f_desc.bits)?,
+            )));
+        }
+    }
+    Ok(None)
+}
+
+/// Generates the regwen trait implementations of all write protected & regwen registers
+///
+/// Multiregs whose copies span several registers but share a single regwen register don't
+/// implement `RegwenProtected`, since the offset to the regwen register differs per copy. The
+/// register block gets `try_set_<name>` & `try_write_<name>` methods for them instead, which check
+/// the shared regwen before writing a copy.
+fn gen_regwen_impls(
+    ctx: &Context,
+    struct_ident: &Ident,
+    registers: &[RegisterDescription],
+    params: &Option<Vec<ParamDescription>>,
+) -> syn::Result<Vec<proc_macro2::TokenStream>> {
+    // Registers are identified by their name & layout, `None` for plain registers
+    let find = |name: &str| -> syn::Result<_> {
+        for (index, reg_desc) in registers.iter().enumerate() {
+            let reg_ctx = ctx.register(index, reg_desc);
+            match reg_desc {
+                RegisterDescription {
+                    name: Some(reg_name),
+                    fields: Some(fields),
+                    swaccess: Some(swaccess),
+                    ..
+                } if reg_name.eq_ignore_ascii_case(name) => {
+                    return Ok(Some((reg_ctx, fields, swaccess, None)))
+                }
+                RegisterDescription {
+                    multireg:
+                        Some(
+                            multireg @ MultiregDescription {
+                                name: reg_name,
+                                fields: Some(fields),
+                                swaccess,
+                                ..
+                            },
+                        ),
+                    ..
+                } if reg_name.eq_ignore_ascii_case(name) => {
+                    let layout = MultiregLayout::new(&reg_ctx, multireg, params)?;
+                    return Ok(Some((reg_ctx, fields, swaccess, Some(layout))));
+                }
+                _ => {}
+            }
+        }
+        Ok(None)
+    };
+
+    let mut impls = vec![];
+    let mut regwens = vec![];
+    for (index, reg_desc) in registers.iter().enumerate() {
+        let reg_ctx = ctx.register(index, reg_desc);
+        let (name, regwen, layout, regwen_multi, shadowed) = match reg_desc {
+            RegisterDescription {
+                name: Some(name),
+                fields: Some(_),
+                regwen: Some(regwen),
+                ..
+            } => (name, regwen, None, false, false),
+            RegisterDescription {
+                multireg:
+                    Some(
+                        multireg @ MultiregDescription {
+                            name,
+                            fields: Some(_),
+                            regwen: Some(regwen),
+                            regwen_multi,
+                            ..
+                        },
+                    ),
+                ..
+            } => (
+                name,
+                regwen,
+                Some(MultiregLayout::new(&reg_ctx, multireg, params)?),
+                regwen_multi.as_deref().is_some_and(is_true),
+                multireg.is_shadowed(),
+            ),
+            _ => continue,
+        };
+        let Some((regwen_ctx, regwen_fields, regwen_swaccess, regwen_layout)) = find(regwen)?
+        else {
+            return Err(reg_ctx
+                .key("regwen")
+                .error(format!("unknown regwen register \"{}\"", regwen)));
+        };
+
+        // Copies of multiregs need to be at the same offset to their regwen copy, multiregs that fit
+        // into a single register behave like plain registers
+        let layout = layout.filter(|layout| layout.array_len().is_some());
+        let regwen_layout = regwen_layout.filter(|layout| layout.array_len().is_some());
+        let shared = match (layout, regwen_layout) {
+            (None, None) => false,
+            (Some(layout), Some(regwen_layout)) if regwen_multi && layout.per_reg == 1 => {
+                if regwen_layout.per_reg != 1 || layout.registers() != regwen_layout.registers() {
+                    return Err(reg_ctx.key("regwen").error(format!(
+                        "regwen {} does not have one copy per copy of the multireg",
+                        regwen
+                    )));
+                }
+                false
+            }
+            (Some(_), None) if !regwen_multi => true,
+            _ => {
+                return Err(reg_ctx.key("regwen").error(format!(
+                    "regwen {} is neither a single register nor one register per register of \
+                     the multireg",
+                    regwen
+                )));
+            }
+        };
+        let reg_name = format_ident!("{}", name.to_lowercase());
+        let regwen_name = format_ident!("{}", regwen.to_lowercase());
+        let mut mask = 0;
+        for (f_index, f_desc) in regwen_fields.iter().enumerate() {
+            mask |= bits_mask(&regwen_ctx.field(f_index, f_desc).key("bits"), &f_desc.bits)?;
+        }
+        if shared {
+            impls.push(gen_shared_regwen_writes(
+                struct_ident,
+                &reg_name,
+                &regwen_name,
+                mask,
+                shadowed,
+            ));
+        } else {
+            impls.push(quote!(
+                impl opentitan_lib::registers::RegwenProtected for #reg_name::Register {
+                    const REGWEN: (isize, u32) = (
+                        core::mem::offset_of!(#struct_ident, #regwen_name) as isize
+                            - core::mem::offset_of!(#struct_ident, #reg_name) as isize,
+                        #mask,
+                    );
+                }
+            ));
+        }
+
+        if regwen_swaccess == "rw0c" && !regwens.contains(&regwen_name) {
+            impls.push(quote!(
+                impl opentitan_lib::registers::RegwenRegister for #regwen_name::Register {
+                    const ENABLE: u32 = #mask;
+                }
+            ));
+            regwens.push(regwen_name);
+        }
+    }
+    Ok(impls)
+}
+
+/// Generates the checked writes to the registers of the multireg `reg_name`, whose copies share the
+/// regwen register `regwen_name` with the enable bits `mask`
+fn gen_shared_regwen_writes(
+    struct_ident: &Ident,
+    reg_name: &Ident,
+    regwen_name: &Ident,
+    mask: u32,
+    shadowed: bool,
+) -> proc_macro2::TokenStream {
+    let try_set = format_ident!("try_set_{}", reg_name);
+    let try_write = format_ident!("try_write_{}", reg_name);
+    let set_doc = format!(
+        "Writes the raw value to the register `index` of [`{}`](Self::{})",
+        reg_name, reg_name
+    );
+    let write_doc = format!(
+        "Writes the fields to the register `index` of [`{}`](Self::{}), all other fields are set \
+         to 0",
+        reg_name, reg_name
+    );
+    let locked_doc = format!(
+        "Returns `Err(WriteError::Locked)` without writing if `{}` is cleared.",
+        regwen_name
+    );
+    // Shadowed registers report update errors of their double write
+    let set = if shadowed {
+        quote!(self.#reg_name[index].set(value))
+    } else {
+        quote!(
+            self.#reg_name[index].set(value);
+            Ok(())
+        )
+    };
+
+    quote!(
+        impl #struct_ident {
+            #[doc = #set_doc]
+            ///
+            #[doc = #locked_doc]
+            ///
+            /// # Panics
+            /// If `index` is out of range
+            pub fn #try_set(
+                &self,
+                index: usize,
+                value: u32,
+            ) -> Result<(), opentitan_lib::registers::WriteError> {
+                use opentitan_lib::tock_registers::interfaces::{Readable, Writeable};
+                if self.#regwen_name.get() & #mask == 0 {
+                    return Err(opentitan_lib::registers::WriteError::Locked);
+                }
+                #set
+            }
+
+            #[doc = #write_doc]
+            ///
+            #[doc = #locked_doc]
+            ///
+            /// # Panics
+            /// If `index` is out of range
+            pub fn #try_write(
+                &self,
+                index: usize,
+                field: opentitan_lib::tock_registers::fields::FieldValue<u32, #reg_name::Register>,
+            ) -> Result<(), opentitan_lib::registers::WriteError> {
+                self.#try_set(index, field.value)
+            }
+        }
+    )
+}
+
+/// Generates the reset value constants and the `check_reset_state` function of a register block
+///
+/// Registers that are not readable without side effects, ie. registers with a read to clear field,
+/// windows and registers excluded from the reset test by their DV tags are not checked.
+fn gen_reset_check(
+    ctx: &Context,
+    gen_intr: bool,
+    registers: &[RegisterDescription],
+    params: &Option<Vec<ParamDescription>>,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let mut consts = vec![];
+    let mut checks = vec![];
+
+    // Multiregs with several registers get the reset value of every register
+    let mut add = |name: &str, readable: bool, values: Vec<(u32, u32)>| {
+        let str_name = name.to_lowercase();
+        let reg_name = format_ident!("{}", str_name);
+        let const_name = format_ident!("{}_RESVAL", name.to_uppercase());
+        if values.len() == 1 {
+            let resval = values[0].0;
+            let doc = format!("Reset value of [`{}`](Self::{})", str_name, str_name);
+            consts.push(quote!(
+                #[doc = #doc]
+                pub const #const_name: u32 = #resval;
+            ));
+        } else {
+            let resvals = values.iter().map(|(resval, _)| resval);
+            let count = values.len();
+            let doc = format!(
+                "Reset values of the registers of [`{}`](Self::{})",
+                str_name, str_name
+            );
+            consts.push(quote!(
+                #[doc = #doc]
+                pub const #const_name: [u32; #count] = [#( #resvals ),*];
+            ));
+        }
+
+        if !readable {
+            return;
+        }
+        if values.len() == 1 {
+            let (expected, mask) = values[0];
+            checks.push(quote!(
+                ok &= opentitan_lib::registers::check_reset_value(
+                    &self.#reg_name, base, #str_name, None, #expected, #mask, &mut on_mismatch,
+                );
+            ));
+        } else {
+            let expected = values.iter().map(|(expected, _)| expected);
+            let mask = values.iter().map(|(_, mask)| mask);
+            checks.push(quote!(
+                for (index, (expected, mask)) in [#( (#expected, #mask) ),*].into_iter().enumerate() {
+                    ok &= opentitan_lib::registers::check_reset_value(
+                        &self.#reg_name[index], base, #str_name, Some(index), expected, mask,
+                        &mut on_mismatch,
+                    );
+                }
+            ));
+        }
+    };
+
+    if gen_intr {
+        add("intr_state", true, vec![(0, u32::MAX)]);
+        add("intr_enable", true, vec![(0, u32::MAX)]);
+    }
+    for (index, reg_desc) in registers.iter().enumerate() {
+        let ctx = ctx.register(index, reg_desc);
+        match reg_desc {
+            RegisterDescription {
+                name: Some(name),
+                swaccess: Some(swaccess),
+                fields: Some(fields),
+                resval,
+                tags,
+                ..
+            } if !excluded_from_reset_test(tags) => {
+                add(
+                    name,
+                    readable_without_side_effects(swaccess, fields),
+                    vec![reset_value(&ctx, fields, resval.as_deref(), 1, false)?],
+                );
+            }
+            RegisterDescription {
+                multireg:
+                    Some(
+                        multireg @ MultiregDescription {
+                            fields: Some(fields),
+                            ..
+                        },
+                    ),
+                ..
+            } if !excluded_from_reset_test(&multireg.tags) => {
+                let layout = MultiregLayout::new(&ctx, multireg, params)?;
+                let values = (0..layout.registers())
+                    .map(|reg| {
+                        let copies = (layout.count - reg * layout.per_reg).min(layout.per_reg);
+                        reset_value(
+                            &ctx,
+                            fields,
+                            multireg.resval.as_deref(),
+                            copies,
+                            layout.compact,
+                        )
+                    })
+                    .collect::<syn::Result<_>>()?;
+                add(
+                    &multireg.name,
+                    readable_without_side_effects(&multireg.swaccess, fields),
+                    values,
+                );
+            }
+            _ => {}
+        }
+    }
+
+    Ok(quote!(
+        #( #consts )*
+
+        /// Compares all registers that can be read without side effects to their reset value
+        ///
+        /// `on_mismatch` is called for every register that differs in a bit with a known reset
+        /// value. Returns whether all registers hold their reset value.
+        pub fn check_reset_state(
+            &self,
+            mut on_mismatch: impl FnMut(&opentitan_lib::registers::ResetMismatch),
+        ) -> bool {
+            let base = self as *const Self as usize;
+            let mut ok = true;
+            #( #checks )*
+            ok
+        }
+    ))
+}
+
+/// Whether a register with the access `swaccess` & the fields `fields` can be read without changing
+/// its value
+fn readable_without_side_effects(swaccess: &str, fields: &[FieldDescription]) -> bool {
+    matches!(swaccess, "ro" | "rw" | "rw1c" | "rw0c" | "rw1s")
+        && !fields
+            .iter()
+            .any(|f_desc| f_desc.swaccess.as_deref() == Some("rc"))
+}
+
+/// Computes the reset value & the mask of bits with a known reset value of a register
+///
+/// Compact registers hold `copies` copies of their single field.
+fn reset_value(
+    ctx: &Context,
+    fields: &[FieldDescription],
+    reg_resval: Option<&str>,
+    copies: usize,
+    compact: bool,
+) -> syn::Result<(u32, u32)> {
+    // Fields without a reset value take it from the register, or reset to 0
+    let reg_resval = match reg_resval {
+        Some(resval) => parse_resval(&ctx.key("resval"), resval)?,
+        None => Some(0),
+    };
+
+    let mut value = 0u32;
+    let mut mask = 0u32;
+    for (index, f_desc) in fields.iter().enumerate() {
+        let ctx = ctx.field(index, f_desc);
+        let (start, width) = bit_range(&ctx.key("bits"), &f_desc.bits)?;
+        let field_mask = if width >= 32 {
+            u32::MAX
+        } else {
+            (1u32 << width) - 1
+        };
+        // Write only fields always read as 0, there is no point in comparing them
+        if matches!(
+            f_desc.swaccess.as_deref(),
+            Some("wo" | "r0w1c" | "w1c" | "w1s")
+        ) {
+            continue;
+        }
+
+        let field_resval = match &f_desc.resval {
+            Some(resval) => Some(parse_resval(&ctx.key("resval"), resval)?),
+            None => None,
+        };
+        for copy in 0..if compact { copies } else { 1 } {
+            let shift = start + copy * width;
+            let resval = match field_resval {
+                Some(resval) => resval,
+                None => reg_resval.map(|resval| resval >> shift),
+            };
+            if let Some(resval) = resval {
+                value |= (resval as u32 & field_mask) << shift;
+                mask |= field_mask << shift;
+            }
+        }
+    }
+    Ok((value, mask))
+}
+
+/// Parses a reset value, `None` if it is unknown (`x`)
+fn parse_resval(ctx: &Context, s: &str) -> syn::Result<Option<u64>> {
+    match s {
+        "true" => Ok(Some(1)),
+        "false" => Ok(Some(0)),
+        "x" | "X" => Ok(None),
+        s => match parse_int(s) {
+            Some(resval) => Ok(Some(resval)),
+            None => Err(ctx.error(format!("invalid reset value \"{}\"", s))),
+        },
+    }
+}
+
+/// Whether the DV tags of a register exclude it from the hardware reset test
+fn excluded_from_reset_test(tags: &Option<Vec<String>>) -> bool {
+    tags.iter().flatten().any(|tag| {
+        let mut parts = tag.split(':');
+        parts.next() == Some("excl")
+            && matches!(parts.next(), Some("CsrHwResetTest" | "CsrAllTests"))
+            && matches!(
+                parts.next(),
+                Some("CsrExclInitCheck" | "CsrExclCheck" | "CsrExclAll")
+            )
+    })
+}
+
+/// Returns the path of the register type implementing the given swaccess
+///
+/// Plain read/write policies use the tock-registers types, all others use the dedicated
+/// types from `opentitan_lib::registers` that only offer the operations valid for them.
+fn register_type(
+    ctx: &Context,
+    swaccess: &str,
+    shadowed: bool,
+) -> syn::Result<proc_macro2::TokenStream> {
+    Ok(match swaccess {
+        "rw" if shadowed => quote!(opentitan_lib::registers::ShadowedReadWrite),
+        "ro" => quote!(opentitan_lib::registers::ReadOnly),
+        "wo" => quote!(opentitan_lib::registers::WriteOnly),
+        "rw" => quote!(opentitan_lib::registers::ReadWrite),
+        "rc" => quote!(opentitan_lib::registers::ReadClear),
+        "rw1c" => quote!(opentitan_lib::registers::ReadWriteOneToClear),
+        "rw0c" => quote!(opentitan_lib::registers::ReadWriteZeroToClear),
+        "r0w1c" => quote!(opentitan_lib::registers::ReadZeroWriteOneToClear),
+        "w1c" => quote!(opentitan_lib::registers::WriteOneToClear),
+        "rw1s" => quote!(opentitan_lib::registers::ReadWriteOneToSet),
+        "w1s" => quote!(opentitan_lib::registers::WriteOneToSet),
+        x => {
+            return Err(ctx.error(format!("unknown swaccess \"{}\"", x)));
+        }
+    })
+}
+
+fn gen_field_values(
+    ctx: &Context,
+    field_values: &[FieldValue],
+    width: usize,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let names = field_values
+        .iter()
+        .map(|f_desc| format_ident!("{}", f_desc.name));
+    let docs = field_values.iter().map(|f_desc| f_desc.desc.clone());
+    let values = field_values
+        .iter()
+        .enumerate()
+        .map(|(index, f_desc)| field_value(&ctx.index("enum", index).key("value"), f_desc, width))
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote!(
+        #(
+            #[doc = #docs]
+            #names = #values
+        ),*
+    ))
+}
+
+/// Parses the value of an enumerated field value, which has to fit into the `width` bits of the
+/// field
+fn field_value(ctx: &Context, f_desc: &FieldValue, width: usize) -> syn::Result<u32> {
+    parse_int(&f_desc.value)
+        .filter(|value| width >= 32 || *value < 1 << width)
+        .and_then(|value| u32::try_from(value).ok())
+        .ok_or_else(|| {
+            ctx.error(format!(
+                "invalid value \"{}\" of {}",
+                f_desc.value, f_desc.name
+            ))
+        })
+}
+
+/// Generates a rust enum for every enumerated field of a register
+///
+/// The enum of the field `mode` of the register `CTRL` is `CtrlMode`, it converts from & to the
+/// raw value of the field & displays the name of the value. Fields of registers that are not
+/// replicated also get a conversion to the `FieldValue` to write.
+fn gen_field_enums(
+    ctx: &Context,
+    register_description: &RegisterDescription,
+    params: &Option<Vec<ParamDescription>>,
+) -> syn::Result<Vec<proc_macro2::TokenStream>> {
+    let (reg_name, fields, copies) = match register_description {
+        RegisterDescription {
+            name: Some(name),
+            fields: Some(fields),
+            ..
+        } => (name, fields, 1),
+        RegisterDescription {
+            multireg:
+                Some(
+                    multireg @ MultiregDescription {
+                        name,
+                        fields: Some(fields),
+                        ..
+                    },
+                ),
+            ..
+        } => {
+            let layout = MultiregLayout::new(ctx, multireg, params)?;
+            let copies = if layout.compact {
+                layout.count.min(layout.per_reg)
+            } else {
+                1
+            };
+            (name, fields, copies)
+        }
+        _ => return Ok(vec![]),
+    };
+
+    let mut enums = vec![];
+    for (index, f_desc) in fields.iter().enumerate() {
+        let Some(values) = &f_desc.values else {
+            continue;
+        };
+        let ctx = ctx.field(index, f_desc);
+        let (_, width) = bit_range(&ctx.key("bits"), &f_desc.bits)?;
+        let field_name = f_desc.name.clone().unwrap_or_else(|| "data".to_owned());
+
+        let enum_name = format_ident!("{}{}", camel_case(reg_name), camel_case(&field_name));
+        let doc = format!(
+            "Values of the field [`{}::{}`]",
+            reg_name.to_lowercase(),
+            field_name.to_lowercase()
+        );
+        let mut variants: Vec<Ident> = vec![];
+        let mut numbers: Vec<u32> = vec![];
+        for (index, value) in values.iter().enumerate() {
+            let ctx = ctx.index("enum", index);
+            let number = field_value(&ctx.key("value"), value, width)?;
+            let name = camel_case(&value.name);
+            let variant = if name.starts_with(|c: char| c.is_ascii_digit()) {
+                format_ident!("Value{}", name)
+            } else {
+                format_ident!("{}", name)
+            };
+            if variants.contains(&variant) {
+                return Err(ctx
+                    .key("name")
+                    .error(format!("duplicate value name {}", value.name)));
+            }
+            if numbers.contains(&number) {
+                return Err(ctx
+                    .key("value")
+                    .error(format!("duplicate value {} of {}", number, value.name)));
+            }
+            variants.push(variant);
+            numbers.push(number);
+        }
+        let docs = values.iter().map(|value| value.desc.clone());
+        let names = values.iter().map(|value| value.name.clone());
+
+        let field_value = (copies == 1).then(|| {
+            let reg = format_ident!("{}", reg_name.to_lowercase());
+            let field = format_ident!("{}", field_name.to_lowercase());
+            quote!(
+                impl From<#enum_name> for opentitan_lib::tock_registers::fields::FieldValue<u32, #reg::Register> {
+                    fn from(value: #enum_name) -> Self {
+                        #reg::#field.val(value as u32)
+                    }
+                }
+            )
+        });
+
+        enums.push(quote!(
+            #[doc = #doc]
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            #[repr(u32)]
+            pub enum #enum_name {
+                #(
+                    #[doc = #docs]
+                    #variants = #numbers,
+                )*
+            }
+
+            impl From<#enum_name> for u32 {
+                fn from(value: #enum_name) -> u32 {
+                    value as u32
+                }
+            }
+
+            impl TryFrom<u32> for #enum_name {
+                type Error = opentitan_lib::Error;
+
+                fn try_from(value: u32) -> Result<Self, opentitan_lib::Error> {
+                    match value {
+                        #(#numbers => Ok(#enum_name::#variants),)*
+                        _ => Err(opentitan_lib::Error::InvalidArgument),
+                    }
+                }
+            }
+
+            impl core::fmt::Display for #enum_name {
+                fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                    f.write_str(match self {
+                        #(#enum_name::#variants => #names,)*
+                    })
+                }
+            }
+
+            #field_value
+        ));
+    }
+    Ok(enums)
+}
+
+/// Returns the mask of the bits `s` (eg. `7:4`) inside the register
+fn bits_mask(ctx: &Context, s: &str) -> syn::Result<u32> {
+    let (start, width) = bit_range(ctx, s)?;
+    Ok(if width >= 32 {
+        u32::MAX
+    } else {
+        ((1u32 << width) - 1) << start
+    })
+}
+
+/// Returns the first bit & the width of the bits `s` (eg. `7:4`) inside a 32 bit register
+fn bit_range(ctx: &Context, s: &str) -> syn::Result<(usize, usize)> {
+    let start = extract_start(ctx, s)?;
+    let end = extract_end(ctx, s)?;
+    if end < start || end >= 32 {
+        return Err(ctx.error(format!("invalid bit range \"{}\"", s)));
+    }
+    Ok((start, end + 1 - start))
+}
+
+fn extract_start(ctx: &Context, s: &str) -> syn::Result<usize> {
+    let start = match s.split_once(':') {
+        Some((_, start)) => start,
+        None => s,
+    };
+    start
+        .trim()
+        .parse()
+        .map_err(|_| ctx.error(format!("invalid start bit in \"{}\"", s)))
+}
+fn extract_end(ctx: &Context, s: &str) -> syn::Result<usize> {
+    let end = match s.split_once(':') {
+        Some((end, _)) => end,
+        None => s,
+    };
+    end.trim()
+        .parse()
+        .map_err(|_| ctx.error(format!("invalid end bit in \"{}\"", s)))
+}
+/// Evaluates a count or size of the IP description, eg. `NumWords` or `NumAlerts * 2`
+fn parse_count(
+    ctx: &Context,
+    s: &str,
+    params: &Option<Vec<ParamDescription>>,
+) -> syn::Result<usize> {
+    eval_param_expr(s, params, 0)
+        .and_then(|value| usize::try_from(value).map_err(|_| "value is too large".to_owned()))
+        .map_err(|error| ctx.error(format!("invalid expression \"{}\": {}", s, error)))
+}
+
+/// Maximum depth of parameters referring to other parameters, deeper nesting is a cycle
+const MAX_PARAM_DEPTH: usize = 16;
+
+/// Evaluates an expression that may refer to the parameters of the IP description
+fn eval_param_expr(
+    s: &str,
+    params: &Option<Vec<ParamDescription>>,
+    depth: usize,
+) -> Result<u64, String> {
+    expr::eval(s, &mut |name| {
+        let param = params.iter().flatten().find(|param| param.name == name)?;
+        Some(match &param.default {
+            None => Err(format!("parameter {} has no value", name)),
+            Some(_) if depth >= MAX_PARAM_DEPTH => {
+                Err(format!("parameter {} refers to itself", name))
+            }
+            Some(default) => eval_param_expr(default, params, depth + 1)
+                .map_err(|error| format!("parameter {}: {}", name, error)),
+        })
+    })
+}
+
+/// Generates the `params` module with a constant for every parameter with a value
+///
+/// Integers are `usize`, single bits `bool` & bit vectors the smallest unsigned type that fits.
+/// Parameters of other types, eg. package enums, or with values that can not be evaluated, eg.
+/// wide random constants, are left out.
+fn gen_params(params: &Option<Vec<ParamDescription>>) -> proc_macro2::TokenStream {
+    let mut docs = vec![];
+    let mut names = vec![];
+    let mut types = vec![];
+    let mut values = vec![];
+    for param in params.iter().flatten() {
+        let Some(default) = &param.default else {
+            continue;
+        };
+        let Some((ty, value)) = param_type(param, params).and_then(|ty| {
+            let value = match default.as_str() {
+                "true" | "True" => 1,
+                "false" | "False" => 0,
+                default => eval_param_expr(default, params, 0).ok()?,
+            };
+            let value = match ty {
+                "bool" if value > 1 => return None,
+                "bool" => (value == 1).to_string(),
+                "usize" => usize::try_from(value).ok()?.to_string(),
+                _ => value.to_string(),
+            };
+            Some((ty, value))
+        }) else {
+            continue;
+        };
+        docs.push(param.desc.clone().unwrap_or_else(|| param.name.clone()));
+        names.push(format_ident!("{}", screaming_snake_case(&param.name)));
+        types.push(format_ident!("{}", ty));
+        values.push(value.parse::<proc_macro2::TokenStream>().unwrap());
+    }
+    if names.is_empty() {
+        return quote!();
+    }
+
+    quote!(
+        /// Hardware parameters of the IP
+        pub mod params {
+            #(
+                #[doc = #docs]
+                pub const #names: #types = #values;
+            )*
+        }
+    )
+}
+
+/// Returns the rust type of the parameter `param`, `None` if it has no integer type
+fn param_type(
+    param: &ParamDescription,
+    params: &Option<Vec<ParamDescription>>,
+) -> Option<&'static str> {
+    let ty = param.ty.as_deref().unwrap_or("int");
+    let ty = ty.split_whitespace().collect::<Vec<_>>().join(" ");
+    match ty.as_str() {
+        "int" | "int unsigned" | "integer" => Some("usize"),
+        "bit" | "logic" => Some("bool"),
+        _ => {
+            let range = ty
+                .strip_prefix("logic")
+                .or_else(|| ty.strip_prefix("bit"))?
+                .trim()
+                .strip_prefix('[')?
+                .strip_suffix(']')?;
+            let (msb, lsb) = range.split_once(':')?;
+            let msb = eval_param_expr(msb.trim(), params, 0).ok()?;
+            let lsb = eval_param_expr(lsb.trim(), params, 0).ok()?;
+            match msb.checked_sub(lsb)? + 1 {
+                1..=8 => Some("u8"),
+                9..=16 => Some("u16"),
+                17..=32 => Some("u32"),
+                33..=64 => Some("u64"),
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Placement of the copies of a multireg following the reggen rules
+#[derive(Clone, Copy)]
+struct MultiregLayout {
+    /// Number of copies described by the multireg
+    count: usize,
+    /// Whether the copies of the single field are packed together
+    compact: bool,
+    /// Number of copies that share one register
+    per_reg: usize,
+}
+
+impl MultiregLayout {
+    fn new(
+        ctx: &Context,
+        multireg: &MultiregDescription,
+        params: &Option<Vec<ParamDescription>>,
+    ) -> syn::Result<Self> {
+        let count = parse_count(&ctx.key("count"), &multireg.count, params)?;
+        if count == 0 {
+            return Err(ctx.key("count").error("multireg has no copies"));
+        }
+        let fields = multireg.fields.as_deref().unwrap_or_default();
+
+        // Like reggen, multiregs with a single field are compacted unless stated otherwise
+        let compact = match multireg.compact.as_deref() {
+            Some(compact) => is_true(compact),
+            None => fields.len() == 1,
+        };
+        if compact && fields.len() != 1 {
+            return Err(ctx
+                .key("compact")
+                .error("compact multireg does not have exactly one field"));
+        }
+
+        let per_reg = if compact {
+            let (start, width) = bit_range(&ctx.field(0, &fields[0]).key("bits"), &fields[0].bits)?;
+            ((32 - start) / width).max(1)
+        } else {
+            1
+        };
+
+        Ok(MultiregLayout {
+            count,
+            compact,
+            per_reg,
+        })
+    }
+
+    /// Number of registers needed to hold all copies
+    fn registers(&self) -> usize {
+        self.count.div_ceil(self.per_reg)
+    }
+
+    /// Array length of the generated register, `None` if a single register suffices
+    fn array_len(&self) -> Option<usize> {
+        match self.registers() {
+            1 => None,
+            len => Some(len),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct IPDescription {
+    interrupt_list: Option<Vec<InterruptDescription>>,
+    #[serde(default)]
+    alert_list: Vec<InterruptDescription>,
+    registers: Vec<RegisterDescription>,
+    param_list: Option<Vec<ParamDescription>>,
+}
+
+/// Register interfaces of an IP, used to compute the size of their address ranges & to list
+/// their registers
+#[derive(Deserialize, Debug)]
+struct BlockDescription {
+    interrupt_list: Option<Vec<InterruptDescription>>,
+    #[serde(default)]
+    alert_list: Vec<InterruptDescription>,
+    registers: RegisterBlocks,
+    param_list: Option<Vec<ParamDescription>>,
+}
+
+/// Registers of an IP with a single interface, or of each of its named interfaces
+#[derive(Debug)]
+enum RegisterBlocks {
+    Single(Vec<RegisterDescription>),
+    Interfaces(OrderedMap<Vec<RegisterDescription>>),
+}
+
+impl<'de> Deserialize<'de> for RegisterBlocks {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BlocksVisitor;
+
+        impl<'de> Visitor<'de> for BlocksVisitor {
+            type Value = RegisterBlocks;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a list of registers or an object of interfaces")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+                Vec::deserialize(SeqAccessDeserializer::new(seq)).map(RegisterBlocks::Single)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                OrderedMap::deserialize(MapAccessDeserializer::new(map))
+                    .map(RegisterBlocks::Interfaces)
+            }
+        }
+
+        deserializer.deserialize_any(BlocksVisitor)
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct InterruptDescription {
+    name: String,
+    desc: String,
+    /// Number of bits of an interrupt, alerts are always a single bit
+    width: Option<String>,
+    /// `event` or `status` for interrupts
+    #[serde(rename = "type")]
+    ty: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RegisterDescription {
+    skipto: Option<String>,
+    window: Option<WindowDescription>,
+    name: Option<String>,
+    desc: Option<String>,
+    swaccess: Option<String>,
+    resval: Option<String>,
+    tags: Option<Vec<String>>,
+    shadowed: Option<String>,
+    update_err_alert: Option<String>,
+    storage_err_alert: Option<String>,
+    regwen: Option<String>,
+    fields: Option<Vec<FieldDescription>>,
+    multireg: Option<MultiregDescription>,
+}
+
+impl RegisterDescription {
+    fn is_shadowed(&self) -> bool {
+        is_shadowed(
+            &self.shadowed,
+            &self.update_err_alert,
+            &self.storage_err_alert,
+        )
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ParamDescription {
+    name: String,
+    #[serde(rename = "type")]
+    ty: Option<String>,
+    desc: Option<String>,
+    default: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct WindowDescription {
+    name: String,
+    desc: Option<String>,
+    swaccess: String,
+    items: String,
+    validbits: Option<String>,
+    #[serde(rename = "byte-write")]
+    byte_write: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct MultiregDescription {
+    name: String,
+    desc: Option<String>,
+    swaccess: String,
+    count: String,
+    cname: Option<String>,
+    compact: Option<String>,
+    resval: Option<String>,
+    tags: Option<Vec<String>>,
+    shadowed: Option<String>,
+    update_err_alert: Option<String>,
+    storage_err_alert: Option<String>,
+    regwen: Option<String>,
+    regwen_multi: Option<String>,
+    fields: Option<Vec<FieldDescription>>,
+}
+
+impl MultiregDescription {
+    fn is_shadowed(&self) -> bool {
+        is_shadowed(
+            &self.shadowed,
+            &self.update_err_alert,
+            &self.storage_err_alert,
+        )
+    }
+}
+
+/// Whether a boolean attribute is set, hjson files spell booleans as `true`, `"true"` or `"1"`
+fn is_true(value: &str) -> bool {
+    value.eq_ignore_ascii_case("true") || value == "1"
+}
+
+/// Whether a register is shadowed, reggen requires shadowed registers to name their alerts
+fn is_shadowed(
+    shadowed: &Option<String>,
+    update_err_alert: &Option<String>,
+    storage_err_alert: &Option<String>,
+) -> bool {
+    match shadowed.as_deref() {
+        Some(shadowed) => is_true(shadowed),
+        None => update_err_alert.is_some() || storage_err_alert.is_some(),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct FieldDescription {
+    bits: String,
+    name: Option<String>,
+    desc: Option<String>,
+    swaccess: Option<String>,
+    resval: Option<String>,
+    #[serde(rename = "enum")]
+    values: Option<Vec<FieldValue>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct FieldValue {
+    name: String,
+    value: String,
+    desc: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn description() -> IPDescription {
+        hjson::from_str(
+            r#"{
+              alert_list: [],
+              param_list: [{ name: "NumWords", default: "8" }],
+              registers: [
+                { name: "CTRL", desc: "Control", swaccess: "rw",
+                  fields: [{ bits: "0", name: "EN", desc: "Enable" }]
+                }
+                { multireg: { name: "KEY", desc: "Key", count: "NumKeys", swaccess: "wo",
+                  fields: [{ bits: "31:0", name: "KEY", desc: "Key" }]
+                } }
+              ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn error_location() {
+        let ip = description();
+        let ctx = Context::new(Span::call_site(), Path::new("hw/ip/foo/data/foo.hjson"));
+        let field = ctx
+            .register(0, &ip.registers[0])
+            .field(0, &ip.registers[0].fields.as_ref().unwrap()[0]);
+        assert_eq!(
+            bit_range(&field.key("bits"), "3:7")
+                .unwrap_err()
+                .to_string(),
+            "hw/ip/foo/data/foo.hjson: registers[0].fields[0].bits (register CTRL, field EN): \
+             invalid bit range \"3:7\""
+        );
+
+        let multireg = ip.registers[1].multireg.as_ref().unwrap();
+        let error =
+            MultiregLayout::new(&ctx.register(1, &ip.registers[1]), multireg, &ip.param_list)
+                .err()
+                .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "hw/ip/foo/data/foo.hjson: registers[1].multireg.count (register KEY): \
+             invalid expression \"NumKeys\": unknown parameter NumKeys"
+        );
+    }
+
+    #[test]
+    fn bit_ranges() {
+        let ctx = Context::new(Span::call_site(), Path::new("foo.hjson"));
+        assert_eq!(bit_range(&ctx, "0").unwrap(), (0, 1));
+        assert_eq!(bit_range(&ctx, "7:4").unwrap(), (4, 4));
+        assert_eq!(bit_range(&ctx, "31:0").unwrap(), (0, 32));
+        assert!(bit_range(&ctx, "32").is_err());
+        assert!(bit_range(&ctx, "a:0").is_err());
+        assert_eq!(bits_mask(&ctx, "7:4").unwrap(), 0xf0);
+    }
+
+    #[test]
+    fn param_overrides() {
+        let mut ip = description();
+        let args: RegistersArgs =
+            syn::parse_str(r#""hw/ip/foo/data/foo.hjson", params(NumWords = NumWords * 2 + 1)"#)
+                .unwrap();
+        override_params(&args.source.name(), &mut ip.param_list, args.params).unwrap();
+        assert_eq!(
+            ip.param_list.as_ref().unwrap()[0].default.as_deref(),
+            Some("17")
+        );
+
+        let args: RegistersArgs =
+            syn::parse_str(r#""hw/ip/foo/data/foo.hjson", params(NumKeys = 4)"#).unwrap();
+        let error =
+            override_params(&args.source.name(), &mut ip.param_list, args.params).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "hw/ip/foo/data/foo.hjson has no parameter NumKeys"
+        );
+
+        assert!(syn::parse_str::<RegistersArgs>(r#""foo.hjson", param(NumWords = 4)"#).is_err());
+    }
+
+    #[test]
+    fn ip_sources() {
+        let hjson = r#"{
+            alert_list: [{ name: "fatal_fault", desc: "Fault" }],
+            param_list: [{ name: "NumWords", default: "8" }],
+            registers: [{ name: "CTRL", desc: "Control", swaccess: "rw",
+                          fields: [{ bits: "0", name: "EN", desc: "Enable" }] }]
+        }"#;
+        let args = quote!(hjson = #hjson, params(NumWords = 4));
+        let code = registers(
+            Path::new("/nonexistent"),
+            args,
+            quote!(
+                pub struct AccelRegisters;
+            ),
+        )
+        .unwrap()
+        .to_string();
+        assert!(code.contains("pub ctrl"));
+        assert!(code.contains("pub const NUM_WORDS : usize = 4"));
+        assert!(code.contains("pub alert_test"));
+        assert!(code.contains("opentitan_lib :: tock_registers :: register_structs !"));
+
+        // Without alerts there is no alert test register, the registers start at offset 0
+        let hjson = r#"{
+            registers: [{ name: "CTRL", desc: "Control", swaccess: "rw",
+                          fields: [{ bits: "0", name: "EN", desc: "Enable" }] }]
+        }"#;
+        let code = registers(
+            Path::new("/nonexistent"),
+            quote!(hjson = #hjson),
+            quote!(
+                pub struct AccelRegisters;
+            ),
+        )
+        .unwrap()
+        .to_string();
+        assert!(code.contains("(0usize => pub ctrl"));
+        assert!(!code.contains("alert_test"));
+        assert!(!code.contains("enum Alert"));
+
+        let args = quote!(hjson = "{ registers: [] ");
+        let error = registers(
+            Path::new("/nonexistent"),
+            args,
+            quote!(
+                struct Accel;
+            ),
+        )
+        .unwrap_err();
+        assert!(error.to_string().starts_with("<inline>:1"));
+
+        let args = quote!(crate = "hw/accel.hjson");
+        let error = registers(
+            Path::new("/nonexistent"),
+            args,
+            quote!(
+                struct Accel;
+            ),
+        )
+        .unwrap_err();
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("hw/accel.hjson");
+        assert!(error
+            .to_string()
+            .starts_with(&format!("Can not read file {}", path.display())));
+
+        let args: RegistersArgs = syn::parse_str(r#"crate = "hw/accel.hjson""#).unwrap();
+        assert_eq!(args.source.name(), "hw/accel.hjson");
+        assert!(syn::parse_str::<RegistersArgs>(r#"path = "accel.hjson""#).is_err());
+    }
+
+    #[test]
+    fn params() {
+        assert_eq!(screaming_snake_case("NumWords"), "NUM_WORDS");
+        assert_eq!(screaming_snake_case("SBoxImpl"), "S_BOX_IMPL");
+        assert_eq!(screaming_snake_case("AES192Enable"), "AES192_ENABLE");
+        assert_eq!(screaming_snake_case("DmemSizeByte"), "DMEM_SIZE_BYTE");
+
+        let params: Option<Vec<ParamDescription>> = Some(
+            hjson::from_str(
+                r#"[
+                    { name: "NumWords", type: "int", default: "8", desc: "Words" },
+                    { name: "Masking", type: "bit", default: "1'b1" },
+                    { name: "Seed", type: "logic [NumWords*4-1:0]", default: "32'hcafe" },
+                    { name: "Key", type: "logic [127:0]", default: "0" },
+                    { name: "Impl", type: "aes_pkg::sbox_impl_e", default: "SBoxImplDom" },
+                    { name: "NoDefault", type: "int" },
+                ]"#,
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            gen_params(&params).to_string(),
+            quote!(
+                /// Hardware parameters of the IP
+                pub mod params {
+                    #[doc = "Words"]
+                    pub const NUM_WORDS: usize = 8;
+                    #[doc = "Masking"]
+                    pub const MASKING: bool = true;
+                    #[doc = "Seed"]
+                    pub const SEED: u32 = 51966;
+                }
+            )
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn field_enums() {
+        let register: RegisterDescription = hjson::from_str(
+            r#"{ name: "CTRL", desc: "Control", swaccess: "rw",
+                 fields: [{ bits: "5:4", name: "MODE", desc: "Mode", enum: [
+                     { value: "0x0", name: "off", desc: "Off" },
+                     { value: "0b10", name: "sha2_256", desc: "SHA2" },
+                     { value: "3", name: "1x", desc: "Single" },
+                 ] }] }"#,
+        )
+        .unwrap();
+        let ctx = Context::new(Span::call_site(), Path::new("foo.hjson"));
+        let enums = gen_field_enums(&ctx.register(0, &register), &register, &None).unwrap();
+        let code = enums[0].to_string();
+        assert!(code.contains("pub enum CtrlMode"));
+        assert!(code.contains("Off = 0u32"));
+        assert!(code.contains("Sha2256 = 2u32"));
+        assert!(code.contains("Value1x = 3u32"));
+        assert!(code.contains("CtrlMode :: Sha2256 => \"sha2_256\""));
+        assert!(code.contains("ctrl :: mode . val (value as u32)"));
+
+        let register: RegisterDescription = hjson::from_str(
+            r#"{ name: "CTRL", desc: "Control", swaccess: "rw",
+                 fields: [{ bits: "1:0", name: "MODE", desc: "Mode", enum: [
+                     { value: "4", name: "big", desc: "Too big" },
+                 ] }] }"#,
+        )
+        .unwrap();
+        let error = gen_field_enums(&ctx.register(0, &register), &register, &None).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "foo.hjson: registers[0].fields[0].enum[0].value (register CTRL, field MODE): \
+             invalid value \"4\" of big"
+        );
+    }
+
+    #[test]
+    fn register_map() {
+        let ip: BlockDescription = hjson::from_str(
+            r#"{
+              interrupt_list: [{ name: "done", desc: "Done" }],
+              alert_list: [{ name: "fatal", desc: "Fatal" }],
+              param_list: [{ name: "NumKeys", default: "2" }],
+              registers: [
+                { name: "CTRL", desc: "Control", swaccess: "rw", resval: "0x5",
+                  fields: [{ bits: "0", name: "EN", desc: "Enable" },
+                           { bits: "2", desc: "Mode", swaccess: "ro" }]
+                }
+                { multireg: { name: "KEY", desc: "Key", count: "NumKeys", swaccess: "wo",
+                  compact: "false", fields: [{ bits: "31:0", name: "KEY", desc: "Key" }]
+                } }
+                { window: { name: "MSG", desc: "Message", items: "4", swaccess: "wo" } }
+              ]
+            }"#,
+        )
+        .unwrap();
+        let ctx = Context::new(Span::call_site(), Path::new("foo.hjson"));
+        let map = map_registers(&ctx, &ip, "null").unwrap().unwrap();
+        let names = map
+            .iter()
+            .map(|register| (register.name.as_str(), register.offset, register.words))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                ("INTR_STATE", 0x0, None),
+                ("INTR_ENABLE", 0x4, None),
+                ("INTR_TEST", 0x8, None),
+                ("ALERT_TEST", 0xc, None),
+                ("CTRL", 0x10, None),
+                ("KEY_0", 0x14, None),
+                ("KEY_1", 0x18, None),
+                ("MSG", 0x1c, Some(4)),
+            ]
+        );
+        assert_eq!(map[3].fields[0].name, "FATAL");
+        assert_eq!(map[4].reset, (0x5, 0x5));
+        assert_eq!(map[4].fields[1].name, "CTRL");
+        assert_eq!(map[4].fields[1].swaccess.as_deref(), Some("ro"));
+    }
+
+    #[test]
+    fn alerts() {
+        let alerts: Vec<InterruptDescription> = hjson::from_str(
+            r#"[
+                { name: "recov_ctrl_update_err", desc: "Update error" },
+                { name: "fatal_fault", desc: "Fault" },
+            ]"#,
+        )
+        .unwrap();
+        let ctx = Context::new(Span::call_site(), Path::new("foo.hjson"));
+        let code = gen_alerts(&ctx, &format_ident!("FooRegisters"), &alerts)
+            .unwrap()
+            .to_string();
+        assert!(code.contains("RecovCtrlUpdateErr = 0u32"));
+        assert!(code.contains("FatalFault = 1u32"));
+        assert!(code.contains("Alert :: RecovCtrlUpdateErr => opentitan_lib :: devices :: alerts :: AlertClass :: Recoverable"));
+        assert!(code.contains(
+            "Alert :: FatalFault => opentitan_lib :: devices :: alerts :: AlertClass :: Fatal"
+        ));
+        assert!(code.contains("impl FooRegisters"));
+
+        let alerts: Vec<InterruptDescription> = hjson::from_str(
+            r#"[{ name: "fatal_fault", desc: "Fault" }, { name: "FATAL_FAULT", desc: "Fault" }]"#,
+        )
+        .unwrap();
+        let error = gen_alerts(&ctx, &format_ident!("FooRegisters"), &alerts).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "foo.hjson: alert_list[1].name: duplicate alert name FATAL_FAULT"
+        );
+        assert!(!is_fatal_alert("recov_alert"));
+        assert!(is_fatal_alert("fatal_alert"));
+        assert!(is_fatal_alert("bus_integ_error"));
+    }
+
+    #[test]
+    fn shadowed() {
+        let registers: Vec<RegisterDescription> = hjson::from_str(
+            r#"[
+                { name: "CTRL", swaccess: "rw", shadowed: "true",
+                  update_err_alert: "recov_ctrl_update_err",
+                  fields: [{ bits: "0", name: "EN" }] },
+                { name: "STATUS", swaccess: "ro",
+                  fields: [{ bits: "3", name: "ALERT_RECOV_CTRL_UPDATE_ERR" }] },
+            ]"#,
+        )
+        .unwrap();
+        let ctx = Context::new(Span::call_site(), Path::new("foo.hjson"));
+        let struct_ident = format_ident!("FooRegisters");
+        let code = gen_shadowed_impls(&ctx, &struct_ident, &registers).unwrap();
+        assert_eq!(code.len(), 1);
+        assert!(code[0]
+            .to_string()
+            .contains("offset_of ! (FooRegisters , status)"));
+        assert!(code[0].to_string().contains("8u32"));
+
+        let error = gen_shadowed_impls(&ctx, &struct_ident, &registers[..1]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "foo.hjson: registers[0].update_err_alert (register CTRL): \
+             no status field reports the update error alert recov_ctrl_update_err"
+        );
+
+        let registers: Vec<RegisterDescription> = hjson::from_str(
+            r#"[
+                { multireg: { name: "CLASS", swaccess: "rw", count: "2", cname: "ALERT",
+                  shadowed: "true", update_err_alert: "recov_update_err",
+                  fields: [{ bits: "1:0", name: "CLASS" }] } },
+            ]"#,
+        )
+        .unwrap();
+        let error = gen_shadowed_impls(&ctx, &struct_ident, &registers).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "foo.hjson: registers[0].multireg.update_err_alert (register CLASS): \
+             update errors of shadowed multiregs are not supported"
+        );
+    }
+
+    #[test]
+    fn regwen() {
+        let registers: Vec<RegisterDescription> = hjson::from_str(
+            r#"[
+                { name: "REGWEN", swaccess: "rw0c", fields: [{ bits: "0", name: "EN" }] },
+                { multireg: { name: "KEY_REGWEN", swaccess: "rw0c", count: "2", cname: "KEY",
+                  compact: "false", fields: [{ bits: "0", name: "EN" }] } },
+                { multireg: { name: "KEY", swaccess: "rw", count: "2", cname: "KEY",
+                  regwen: "KEY_REGWEN", regwen_multi: "True", fields: [{ bits: "31:0" }] } },
+                { multireg: { name: "MODE", swaccess: "rw", count: "2", cname: "MODE",
+                  regwen: "REGWEN", fields: [{ bits: "3:0" }] } },
+                { multireg: { name: "SEED", swaccess: "rw", count: "2", cname: "SEED",
+                  regwen: "REGWEN", fields: [{ bits: "31:0" }] } },
+            ]"#,
+        )
+        .unwrap();
+        let ctx = Context::new(Span::call_site(), Path::new("foo.hjson"));
+        let struct_ident = format_ident!("FooRegisters");
+        let code = gen_regwen_impls(&ctx, &struct_ident, &registers, &None)
+            .unwrap()
+            .iter()
+            .map(|impl_| impl_.to_string())
+            .collect::<Vec<_>>();
+        // KEY & the compacted MODE are protected, the copies of SEED span two registers
+        assert!(code[0].contains("RegwenProtected for key :: Register"));
+        assert!(code[1].contains("RegwenRegister for key_regwen :: Register"));
+        assert!(code[2].contains("RegwenProtected for mode :: Register"));
+        assert!(code[3].contains("RegwenRegister for regwen :: Register"));
+        // The copies of SEED are written through the block, which checks the shared regwen
+        assert!(code[4].contains("pub fn try_set_seed (& self , index : usize , value : u32 ,)"));
+        assert!(code[4].contains(
+            "if self . regwen . get () & 1u32 == 0 { \
+             return Err (opentitan_lib :: registers :: WriteError :: Locked) ; } \
+             self . seed [index] . set (value) ; Ok (())"
+        ));
+        assert!(code[4].contains("pub fn try_write_seed"));
+        assert_eq!(code.len(), 5);
+
+        let mut counter = 0;
+        let seed = gen_register(&ctx, &mut counter, &registers[4], &None)
+            .unwrap()
+            .to_string();
+        assert!(seed.contains("The copies are write protected by `regwen`"));
+        assert!(seed.contains("(Self::try_write_seed)"));
+        let key = gen_register(&ctx, &mut counter, &registers[2], &None)
+            .unwrap()
+            .to_string();
+        assert!(!key.contains("write protected"));
+
+        let registers: Vec<RegisterDescription> = hjson::from_str(
+            r#"[
+                { multireg: { name: "KEY_REGWEN", swaccess: "rw0c", count: "4", cname: "KEY",
+                  compact: "false", fields: [{ bits: "0", name: "EN" }] } },
+                { multireg: { name: "KEY", swaccess: "rw", count: "2", cname: "KEY",
+                  regwen: "KEY_REGWEN", regwen_multi: "1", fields: [{ bits: "31:0" }] } },
+            ]"#,
+        )
+        .unwrap();
+        let error = gen_regwen_impls(&ctx, &struct_ident, &registers, &None).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "foo.hjson: registers[1].multireg.regwen (register KEY): \
+             regwen KEY_REGWEN does not have one copy per copy of the multireg"
+        );
+    }
+
+    #[test]
+    fn reset_check() {
+        let registers: Vec<RegisterDescription> = hjson::from_str(
+            r#"[
+                { name: "CTRL", swaccess: "rw", resval: "0x3", fields: [{ bits: "1:0" }] },
+                { name: "EVENTS", swaccess: "ro", fields: [{ bits: "0", name: "A" },
+                  { bits: "1", name: "B", swaccess: "rc", resval: "1" }] },
+                { multireg: { name: "NIBBLES", swaccess: "rw", count: "10", cname: "NIB",
+                  fields: [{ bits: "3:0", resval: "5" }] } },
+            ]"#,
+        )
+        .unwrap();
+        let ctx = Context::new(Span::call_site(), Path::new("foo.hjson"));
+        let code = gen_reset_check(&ctx, false, &registers, &None)
+            .unwrap()
+            .to_string();
+        assert!(code.contains("pub const CTRL_RESVAL : u32 = 3u32"));
+        assert!(code.contains("pub const EVENTS_RESVAL : u32 = 2u32"));
+        // The compacted copies of the second register are part of its reset value
+        assert!(
+            code.contains("pub const NIBBLES_RESVAL : [u32 ; 2usize] = [1431655765u32 , 85u32]")
+        );
+        assert!(code.contains("& self . ctrl ,"));
+        assert!(code.contains("& self . nibbles [index] ,"));
+        // Reading EVENTS would clear B
+        assert!(!code.contains("& self . events"));
+    }
+
+    #[test]
+    fn interrupts() {
+        let interrupts: Vec<InterruptDescription> = hjson::from_str(
+            r#"[
+                { name: "done", desc: "Done" },
+                { name: "fifo_empty", type: "status", desc: "Empty" },
+                { name: "gpio", width: "2", desc: "Pins" },
+            ]"#,
+        )
+        .unwrap();
+        let ctx = Context::new(Span::call_site(), Path::new("foo.hjson"));
+        let bits = interrupt_bits(&ctx, &interrupts).unwrap();
+        assert_eq!(bits, [(0, 1), (1, 1), (2, 2)]);
+        let code = gen_interrupts(&ctx, &format_ident!("FooRegisters"), &interrupts, &bits)
+            .unwrap()
+            .to_string();
+        assert!(code.contains("Done = 0u32"));
+        assert!(code.contains("Gpio0 = 2u32"));
+        assert!(code.contains("Gpio1 = 3u32"));
+        assert!(code.contains("Interrupt :: Gpio1 => \"gpio_1\""));
+        assert!(code.contains("Interrupt :: Done => false"));
+        assert!(code.contains("Interrupt :: FifoEmpty => true"));
+        assert!(code.contains("impl opentitan_lib :: registers :: Interrupts for FooRegisters"));
+
+        let interrupts: Vec<InterruptDescription> =
+            hjson::from_str(r#"[{ name: "done", type: "level", desc: "Done" }]"#).unwrap();
+        let error = gen_interrupts(&ctx, &format_ident!("FooRegisters"), &interrupts, &[(0, 1)])
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "foo.hjson: interrupt_list[0].type: unknown interrupt type \"level\""
+        );
+
+        let interrupts: Vec<InterruptDescription> = hjson::from_str(
+            r#"[{ name: "a", width: "31", desc: "A" }, { name: "b", width: "2", desc: "B" }]"#,
+        )
+        .unwrap();
+        let error = interrupt_bits(&ctx, &interrupts).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "foo.hjson: interrupt_list[1]: the interrupts do not fit into 32 bits"
+        );
+    }
+}
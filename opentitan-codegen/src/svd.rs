@@ -0,0 +1,369 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use proc_macro2::Span;
+use syn::LitStr;
+
+use crate::{
+    addresses::{ip_path, signal_ids, top_dir, top_interfaces, SignalId},
+    registers::{self, IpSource, MapRegister, RegistersArgs},
+};
+
+/// Peripheral of the SVD file, a register interface of a module
+struct Peripheral {
+    name: String,
+    /// Peripheral with the same registers that this one is derived from
+    derived_from: Option<String>,
+    group: String,
+    desc: String,
+    base: usize,
+    size: usize,
+    registers: Vec<MapRegister>,
+    interrupts: Vec<SignalId>,
+}
+
+/// Generates a CMSIS-SVD description of the top described at `top_path`
+///
+/// Every register interface of the modules is a peripheral, named like its constant in the
+/// `addresses` module, eg. `UART0` or `FLASH_CTRL_CORE`. Memories are not listed. IPs described
+/// by one of the `#[registers]` attributes with the arguments `registers_args` use the parameter
+/// overrides given there, so the registers match the ones of the generated code.
+pub fn svd(
+    opentitan_path: &Path,
+    top_path: &str,
+    registers_args: &[proc_macro2::TokenStream],
+) -> Result<String, String> {
+    let span = Span::call_site();
+    let error = |error: syn::Error| error.to_string();
+    let (top, interfaces) =
+        top_interfaces(opentitan_path, &LitStr::new(top_path, span)).map_err(error)?;
+    let registers_args = registers_args
+        .iter()
+        .map(|args| syn::parse2::<RegistersArgs>(args.clone()))
+        .collect::<syn::Result<Vec<_>>>()
+        .map_err(error)?;
+    let mut interrupts =
+        signal_ids(span, 1, top.interrupt_list.as_deref().unwrap_or_default()).map_err(error)?;
+
+    let top_dir = top_dir(top_path);
+    let mut peripherals: Vec<Peripheral> = vec![];
+    for interface in interfaces.iter().filter(|interface| !interface.memory) {
+        let Some(module) = top
+            .module
+            .iter()
+            .find(|module| module.name == interface.module)
+        else {
+            continue;
+        };
+        let kind = interface.interface.as_deref().unwrap_or("null");
+        let derived_from = peripherals
+            .iter()
+            .find(|other| {
+                other.derived_from.is_none()
+                    && interfaces.iter().any(|i| {
+                        i.name == other.name
+                            && i.ty == interface.ty
+                            && i.interface == interface.interface
+                    })
+            })
+            .map(|other| other.name.clone());
+
+        let registers = match derived_from {
+            Some(_) => vec![],
+            None => {
+                // The IP description used by the lib, or the one of the top
+                let args = registers_args.iter().find_map(|args| match &args.source {
+                    IpSource::OpenTitan(path)
+                        if Path::new(&path.value())
+                            .file_stem()
+                            .is_some_and(|stem| *stem == *interface.ty) =>
+                    {
+                        Some((path, args))
+                    }
+                    _ => None,
+                });
+                let (path, params): (PathBuf, _) = match args {
+                    Some((path, args)) => (path.value().into(), args.params.clone()),
+                    None => (
+                        ip_path(span, opentitan_path, &top_dir, module).map_err(error)?,
+                        vec![],
+                    ),
+                };
+                registers::interface_registers(span, opentitan_path, &path, kind, params)
+                    .map_err(error)?
+                    .unwrap_or_default()
+            }
+        };
+
+        // Interrupts are raised by the first register interface of a module
+        let (own, others) = interrupts
+            .into_iter()
+            .partition(|signal| signal.module == interface.module);
+        interrupts = others;
+
+        peripherals.push(Peripheral {
+            name: interface.name.clone(),
+            derived_from,
+            group: interface.ty.to_uppercase(),
+            desc: format!("{} {}", interface.module, interface.kind.replace('`', "")),
+            base: interface.base,
+            size: interface.size,
+            registers,
+            interrupts: own,
+        });
+    }
+
+    let name = top_dir.file_name().map_or_else(
+        || top_path.to_owned(),
+        |name| name.to_string_lossy().into_owned(),
+    );
+    Ok(write_device(&name, top_path, &peripherals))
+}
+
+/// Writes the SVD file of the device `name` with the peripherals `peripherals`
+fn write_device(name: &str, top_path: &str, peripherals: &[Peripheral]) -> String {
+    let mut xml = Xml::default();
+    xml.line(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+    xml.line(&format!(
+        "<!-- Generated by opentitan-regtool from {}, do not edit. -->",
+        escape(top_path)
+    ));
+    xml.open(
+        "device",
+        r#" schemaVersion="1.3" xmlns:xs="http://www.w3.org/2001/XMLSchema-instance" xs:noNamespaceSchemaLocation="CMSIS-SVD.xsd""#,
+    );
+    xml.element("vendor", "lowRISC");
+    xml.element("name", name.to_uppercase());
+    xml.element("version", "1.0");
+    xml.element("description", format!("OpenTitan {}", name));
+    xml.element("addressUnitBits", 8);
+    xml.element("width", 32);
+    xml.element("size", 32);
+    xml.element("access", "read-write");
+    xml.element("resetValue", "0x00000000");
+    xml.element("resetMask", "0xffffffff");
+
+    xml.open("peripherals", "");
+    for peripheral in peripherals {
+        let attrs = match &peripheral.derived_from {
+            Some(derived_from) => format!(r#" derivedFrom="{}""#, escape(derived_from)),
+            None => String::new(),
+        };
+        xml.open("peripheral", &attrs);
+        xml.element("name", &peripheral.name);
+        xml.element("description", &peripheral.desc);
+        xml.element("groupName", &peripheral.group);
+        xml.element("baseAddress", format!("{:#010x}", peripheral.base));
+        xml.open("addressBlock", "");
+        xml.element("offset", 0);
+        xml.element("size", format!("{:#x}", peripheral.size));
+        xml.element("usage", "registers");
+        xml.close("addressBlock");
+        for interrupt in &peripheral.interrupts {
+            xml.open("interrupt", "");
+            xml.element("name", interrupt.name.to_uppercase());
+            xml.element("description", &interrupt.desc);
+            xml.element("value", interrupt.id);
+            xml.close("interrupt");
+        }
+        if !peripheral.registers.is_empty() {
+            xml.open("registers", "");
+            for register in &peripheral.registers {
+                write_register(&mut xml, register);
+            }
+            xml.close("registers");
+        }
+        xml.close("peripheral");
+    }
+    xml.close("peripherals");
+    xml.close("device");
+    xml.out
+}
+
+fn write_register(xml: &mut Xml, register: &MapRegister) {
+    xml.open("register", "");
+    // Windows are arrays of words
+    match register.words {
+        Some(words) => {
+            xml.element("dim", words);
+            xml.element("dimIncrement", 4);
+            xml.element("name", format!("{}[%s]", register.name));
+        }
+        None => xml.element("name", &register.name),
+    }
+    xml.element("description", description(&register.desc));
+    xml.element("addressOffset", format!("{:#x}", register.offset));
+    xml.element("size", 32);
+    // The side effects follow the reset value in the SVD schema
+    let (access, write, read) = sw_access(&register.swaccess);
+    xml.element("access", access);
+    if register.words.is_none() {
+        xml.element("resetValue", format!("{:#010x}", register.reset.0));
+        xml.element("resetMask", format!("{:#010x}", register.reset.1));
+    }
+    write_side_effects(xml, write, read);
+
+    if !register.fields.is_empty() {
+        xml.open("fields", "");
+        for field in &register.fields {
+            xml.open("field", "");
+            xml.element("name", &field.name);
+            xml.element("description", description(&field.desc));
+            xml.element("bitOffset", field.start);
+            xml.element("bitWidth", field.width);
+            if let Some(swaccess) = &field.swaccess {
+                let (access, write, read) = sw_access(swaccess);
+                xml.element("access", access);
+                write_side_effects(xml, write, read);
+            }
+            if !field.values.is_empty() {
+                xml.open("enumeratedValues", "");
+                for (name, desc, value) in &field.values {
+                    xml.open("enumeratedValue", "");
+                    xml.element("name", name);
+                    xml.element("description", desc);
+                    xml.element("value", value);
+                    xml.close("enumeratedValue");
+                }
+                xml.close("enumeratedValues");
+            }
+            xml.close("field");
+        }
+        xml.close("fields");
+    }
+    xml.close("register");
+}
+
+/// Returns the access, the side effect of writes & the one of reads of a swaccess
+fn sw_access(swaccess: &str) -> (&'static str, Option<&'static str>, Option<&'static str>) {
+    match swaccess {
+        "ro" => ("read-only", None, None),
+        "rc" => ("read-only", None, Some("clear")),
+        "wo" => ("write-only", None, None),
+        "w1c" => ("write-only", Some("oneToClear"), None),
+        "w1s" => ("write-only", Some("oneToSet"), None),
+        "rw1c" | "r0w1c" => ("read-write", Some("oneToClear"), None),
+        "rw0c" => ("read-write", Some("zeroToClear"), None),
+        "rw1s" => ("read-write", Some("oneToSet"), None),
+        _ => ("read-write", None, None),
+    }
+}
+
+fn write_side_effects(xml: &mut Xml, write: Option<&str>, read: Option<&str>) {
+    if let Some(write) = write {
+        xml.element("modifiedWriteValues", write);
+    }
+    if let Some(read) = read {
+        xml.element("readAction", read);
+    }
+}
+
+fn description(desc: &Option<String>) -> &str {
+    desc.as_deref().unwrap_or("<no description>")
+}
+
+/// Indented XML output
+#[derive(Default)]
+struct Xml {
+    out: String,
+    depth: usize,
+}
+
+impl Xml {
+    fn line(&mut self, line: &str) {
+        self.out.push_str(&"  ".repeat(self.depth));
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+
+    /// Opens the element `tag`, `attrs` are written as given
+    fn open(&mut self, tag: &str, attrs: &str) {
+        self.line(&format!("<{}{}>", tag, attrs));
+        self.depth += 1;
+    }
+
+    fn close(&mut self, tag: &str) {
+        self.depth -= 1;
+        self.line(&format!("</{}>", tag));
+    }
+
+    /// Writes an element with text, whitespace is collapsed since descriptions span lines
+    fn element(&mut self, tag: &str, text: impl Display) {
+        let text = text.to_string();
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        self.line(&format!("<{0}>{1}</{0}>", tag, escape(&text)));
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registers::MapField;
+
+    #[test]
+    fn device() {
+        let register = |name: &str, swaccess: &str, words| MapRegister {
+            name: name.to_owned(),
+            desc: Some("Status\n  of the <IP>".to_owned()),
+            offset: 0x10,
+            swaccess: swaccess.to_owned(),
+            reset: (0x5, 0xf),
+            fields: vec![MapField {
+                name: "DONE".to_owned(),
+                desc: None,
+                start: 1,
+                width: 2,
+                swaccess: Some("rc".to_owned()),
+                values: vec![("IDLE".to_owned(), "Idle".to_owned(), 2)],
+            }],
+            words,
+        };
+        let peripheral = |name: &str, derived_from: Option<&str>| Peripheral {
+            name: name.to_owned(),
+            derived_from: derived_from.map(str::to_owned),
+            group: "UART".to_owned(),
+            desc: "uart0 registers".to_owned(),
+            base: 0x4000_0000,
+            size: 0x40,
+            registers: vec![
+                register("STATUS", "w1c", None),
+                register("FIFO", "ro", Some(4)),
+            ],
+            interrupts: vec![],
+        };
+        let svd = write_device(
+            "top_earlgrey",
+            "hw/top_earlgrey/data/top_earlgrey.hjson",
+            &[
+                peripheral("UART0", None),
+                peripheral("UART1", Some("UART0")),
+            ],
+        );
+
+        assert!(svd.contains("<name>TOP_EARLGREY</name>"));
+        assert!(svd.contains(r#"<peripheral derivedFrom="UART0">"#));
+        assert!(svd.contains("<baseAddress>0x40000000</baseAddress>"));
+        assert!(svd.contains(
+            "<description>Status of the &lt;IP&gt;</description>\n          \
+             <addressOffset>0x10</addressOffset>\n          \
+             <size>32</size>\n          \
+             <access>write-only</access>\n          \
+             <resetValue>0x00000005</resetValue>\n          \
+             <resetMask>0x0000000f</resetMask>\n          \
+             <modifiedWriteValues>oneToClear</modifiedWriteValues>"
+        ));
+        assert!(svd.contains("<readAction>clear</readAction>"));
+        assert!(svd.contains("<dim>4</dim>"));
+        assert!(svd.contains("<name>FIFO[%s]</name>"));
+        assert!(svd.contains("<description>&lt;no description&gt;</description>"));
+    }
+}
@@ -1,15 +1,217 @@
+//! Writes the linker scripts to the output directory & selects the top
+//!
+//! The top is chosen with the `top_*` features of the lib, eg. `top_englishbreakfast`, and
+//! defaults to Earl Grey. Every IP type of the top is passed to rustc as
+//! `--cfg opentitan_ip="<type>"`, so the drivers of IPs the top does not contain are left out.
+//! They are replaced by stubs that fail to compile with an error naming the IP & the top when they
+//! are used.
+//!
+//! The `MEMORY` regions are generated from the memories of the top description, or taken from
+//! `src/generated/memory.ld` with the `pregenerated` feature. Applications choose the regions for
+//! code & data with the `OPENTITAN_CODE_REGION` (default `eflash`) and `OPENTITAN_DATA_REGION`
+//! (default `ram_main`) environment variables, eg. in the `[env]` table of `.cargo/config.toml`.
+
 use std::env;
 use std::fs;
 use std::path::PathBuf;
 
+/// Tops that can be selected with the `top_<name>` features & their descriptions
+///
+/// The first one is used if no top feature is enabled. The descriptions have to match the ones of
+/// the `addresses!` invocations in `src/devices/mod.rs`.
+const TOPS: &[(&str, &str)] = &[
+    ("earlgrey", "hw/top_earlgrey/data/top_earlgrey.hjson"),
+    (
+        "englishbreakfast",
+        "hw/top_englishbreakfast/data/top_englishbreakfast.hjson",
+    ),
+    ("darjeeling", "hw/top_darjeeling/data/top_darjeeling.hjson"),
+];
+
+/// Drivers of IPs that not all tops contain, their module & main item
+///
+/// The item is the driver type, created from the handle of an instance, or a trait for drivers
+/// without a type. The modules have to match the drivers gated on `opentitan_ip` in
+/// `src/devices/mod.rs`.
+const DRIVERS: &[(&str, Item)] = &[
+    ("aes", Item::Type("Aes")),
+    ("csrng", Item::Type("Csrng")),
+    ("hmac", Item::Type("Hmac")),
+    ("otbn", Item::Trait("Otbn")),
+];
+
+enum Item {
+    Type(&'static str),
+    Trait(&'static str),
+}
+
+/// IP types of the top written by `opentitan-regtool`, one per line
+const PREGENERATED_IPS: &str = "src/generated/ips.txt";
+
+/// Areas at the start of memory regions that are used by the boot ROM
+const RESERVED: &[(&str, u64)] = &[("ram_main", 0x650)];
+
 fn main() {
     let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
 
+    let (top_name, top) = top();
+    println!("cargo:rustc-cfg=opentitan_top=\"{}\"", top_name);
+    println!(
+        "cargo:rustc-check-cfg=cfg(opentitan_top, values({}))",
+        TOPS.iter()
+            .map(|(name, _)| format!("\"{}\"", name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    println!("cargo:rustc-check-cfg=cfg(opentitan_ip, values(any()))");
+
+    let (mut memory_map, ips) = if env::var_os("CARGO_FEATURE_PREGENERATED").is_some() {
+        println!("cargo:rerun-if-changed=src/generated/memory.ld");
+        println!("cargo:rerun-if-changed={}", PREGENERATED_IPS);
+        let memory_map = pregenerated("src/generated/memory.ld", top_name, top);
+        let ips = pregenerated(PREGENERATED_IPS, top_name, top);
+        let ips = ips
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_owned)
+            .collect();
+        (memory_map, ips)
+    } else {
+        let opentitan_path = opentitan_codegen::get_opentitan_path();
+        println!("cargo:rerun-if-env-changed=OPENTITAN_PATH");
+        println!(
+            "cargo:rerun-if-changed={}",
+            opentitan_path.join(top).display()
+        );
+        let memory_map = opentitan_codegen::memory_map(&opentitan_path, top)
+            .unwrap_or_else(|error| panic!("{}", error));
+        let ips = opentitan_codegen::ip_types(&opentitan_path, top)
+            .unwrap_or_else(|error| panic!("{}", error));
+        (memory_map, ips)
+    };
+    for ip in &ips {
+        println!("cargo:rustc-cfg=opentitan_ip=\"{}\"", ip);
+    }
+    fs::write(
+        out_dir.join("missing_drivers.rs"),
+        missing_drivers(top_name, &ips),
+    )
+    .unwrap();
+
+    let code = region("OPENTITAN_CODE_REGION", "eflash");
+    let data = region("OPENTITAN_DATA_REGION", "ram_main");
+    let reserved = |region: &str| {
+        RESERVED
+            .iter()
+            .find(|(name, _)| *name == region)
+            .map_or(0, |(_, size)| *size)
+    };
+    memory_map.push('\n');
+    for (name, size) in RESERVED {
+        memory_map.push_str(&format!("_reserved_{} = {:#x};\n", name, size));
+    }
+    memory_map.push_str(&format!(
+        "\nREGION_ALIAS(\"code\", {});\nREGION_ALIAS(\"data\", {});\n",
+        code, data
+    ));
+    memory_map.push_str(&format!(
+        "_code_reserved = {:#x};\n_data_reserved = {:#x};\n",
+        reserved(&code),
+        reserved(&data)
+    ));
+
     // Put the linker script somewhere the linker can find it.
+    fs::write(out_dir.join("regions.ld"), memory_map).unwrap();
     fs::write(out_dir.join("layout.ld"), include_bytes!("layout.ld")).unwrap();
     fs::write(out_dir.join("memory.ld"), include_bytes!("memory.ld")).unwrap();
     println!("cargo:rustc-link-search={}", out_dir.display());
-    println!("cargo:rerun-if-changed=memory.x");
-    println!("cargo:rerun-if-changed=layout.x");
+    println!("cargo:rerun-if-changed=memory.ld");
+    println!("cargo:rerun-if-changed=layout.ld");
     println!("cargo:rerun-if-changed=build.rs");
 }
+
+/// Returns the name & description of the top selected by the `top_*` features
+fn top() -> (&'static str, &'static str) {
+    let selected = TOPS
+        .iter()
+        .filter(|(name, _)| {
+            let feature = format!("CARGO_FEATURE_TOP_{}", name.to_ascii_uppercase());
+            env::var_os(feature).is_some()
+        })
+        .collect::<Vec<_>>();
+    match selected.as_slice() {
+        [] => TOPS[0],
+        [top] => **top,
+        _ => panic!(
+            "only one top can be selected, but the features {} are enabled",
+            selected
+                .iter()
+                .map(|(name, _)| format!("top_{}", name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Reads the file `path` written by `opentitan-regtool` & checks that it was generated for `top`
+fn pregenerated(path: &str, top_name: &str, top: &str) -> String {
+    let content = fs::read_to_string(path)
+        .unwrap_or_else(|_| panic!("{} is missing, run opentitan-regtool", path));
+    let header = content.lines().next().unwrap_or_default();
+    if !header.contains(&format!("from {},", top)) {
+        panic!(
+            "{} was not generated for the selected top {}, run opentitan-regtool --top {}",
+            path, top_name, top_name
+        );
+    }
+    content
+}
+
+/// Returns the stubs of the driver modules whose IP is not in the IP types `ips` of the top
+///
+/// The stubs only declare the main item of the driver, deprecated with a note naming the IP & the
+/// top. Creating a driver, or implementing the trait, requires a trait no type implements, which
+/// fails with the same message.
+fn missing_drivers(top_name: &str, ips: &[String]) -> String {
+    let mut code = String::new();
+    for (module, item) in DRIVERS {
+        if ips.iter().any(|ip| ip == module) {
+            continue;
+        }
+        let message = format!("the top `{}` has no {} IP", top_name, module);
+        code.push_str(&format!(
+            "/// Stub, {message}\n\
+             pub mod {module} {{\n\
+             #[diagnostic::on_unimplemented(message = \"{message}\", \
+             label = \"the {module} driver is only available for tops with the IP\")]\n\
+             #[doc(hidden)]\n\
+             pub trait InTop {{}}\n\n"
+        ));
+        match item {
+            Item::Type(name) => code.push_str(&format!(
+                "/// Stub, {message}\n\
+                 #[deprecated(note = \"{message}\")]\n\
+                 pub struct {name}<P>(P);\n\n\
+                 #[allow(deprecated)]\n\
+                 impl<P: InTop> {name}<P> {{\n\
+                 pub fn new(handle: P) -> Self {{\n\
+                 {name}(handle)\n\
+                 }}\n\
+                 }}\n"
+            )),
+            Item::Trait(name) => code.push_str(&format!(
+                "/// Stub, {message}\n\
+                 #[deprecated(note = \"{message}\")]\n\
+                 pub trait {name}: InTop {{}}\n"
+            )),
+        }
+        code.push_str("}\n");
+    }
+    code
+}
+
+/// Returns the memory region chosen by the environment variable `var`
+fn region(var: &str, default: &str) -> String {
+    println!("cargo:rerun-if-env-changed={}", var);
+    env::var(var).unwrap_or_else(|_| default.to_owned())
+}
@@ -3,45 +3,39 @@
 //! TODO:
 //!     - make functions on AesRegisters unsafe by default
 //!     - implementation for AesRaw
-//!     - implementation for Aes
 
-use super::addresses;
-use opentitan_macros::registers;
+use super::{
+    peripherals::{self, ip},
+    platform, Peripheral,
+};
+use crate::Error;
 use tock_registers::{
     fields::FieldValue,
     interfaces::{Readable, Writeable},
 };
 
-#[registers("hw/ip/aes/data/aes.hjson")]
+#[cfg(not(feature = "pregenerated"))]
+#[opentitan_macros::registers("hw/ip/aes/data/aes.hjson")]
 pub struct AesRegisters;
-
-const AES: *mut AesRegisters = addresses::AES as *mut AesRegisters;
+#[cfg(feature = "pregenerated")]
+include!("../generated/devices/aes.rs");
 
 /// Returns a pointer to the registers of the aes IP
 ///
-/// This should only be used if either [`AesRaw`] or [`Aes`] do not meet the
-/// requirements (eg. performance or functionality)
-///
 /// # Safety
-/// Reading and modifying the aes registers may have potential side effects.
-/// Usage of the returned pointer is therefore inherently unsafe.
+/// The aes IP must not be used through its handle at the same time.
+#[deprecated(note = "create a `Aes` from the handle of `Peripherals::take`, see `Aes::registers`")]
 pub unsafe fn get_aes_registers() -> *const AesRegisters {
-    AES
+    peripherals::Aes::ADDRESS.ptr()
 }
 
-/// Returns a pointer to the aes IP
-///
-/// This should only be used if [`Aes`] does not meet the
-/// requirements (eg. performance or functionality)
-///
-/// The returned value is an unsafe wrapper for the [`AesRegisters`] struct
-/// that implements a set of commonly used functionality.
+/// Returns a pointer to the aes IP, an unsafe wrapper that implements commonly used functionality
 ///
 /// # Safety
-/// Reading and modifying the aes registers may have potential side effects.
-/// Usage of the returned pointer is therefore inherently unsafe.
+/// The aes IP must not be used through its handle at the same time.
+#[deprecated(note = "create a `Aes` from the handle of `Peripherals::take`, see `Aes::registers`")]
 pub unsafe fn get_aes_raw() -> *mut impl AesRaw {
-    AES
+    peripherals::Aes::ADDRESS.ptr::<AesRegisters>()
 }
 
 pub enum Operation {
@@ -101,6 +95,9 @@ impl KeyLength {
 }
 
 pub trait AesRaw {
+    /// Returns `Err(Error::RecoverableAlert)` if the aes IP reported an update error of the
+    /// control register, `Err(Error::FatalAlert)` after a fatal fault & `Err(Error::Timeout)` if
+    /// it does not become idle
     unsafe fn configure(
         &mut self,
         mode: Mode,
@@ -108,11 +105,25 @@ pub trait AesRaw {
         key_length: KeyLength,
         key_share0: &[u32; 8],
         key_share1: &[u32; 8],
-    );
+    ) -> Result<(), Error>;
+
+    /// Returns `Err(Error::InvalidArgument)` if `input` is not a multiple of the block size or
+    /// `output` is shorter, `Err(Error::FatalAlert)` after a fatal fault & `Err(Error::Timeout)`
+    /// if a block is not processed in time
+    unsafe fn execute(&mut self, input: &[u32], output: &mut [u32]) -> Result<(), Error>;
 
-    unsafe fn execute(&mut self, input: &[u32], output: &mut [u32]);
+    /// Returns `Err(Error::RecoverableAlert)` if the aes IP reported an update error of the
+    /// control register, `Err(Error::Timeout)` if it does not become idle
+    unsafe fn deinitialize(&self) -> Result<(), Error>;
+}
 
-    unsafe fn deinitialize(&self);
+impl AesRegisters {
+    fn check_fatal_fault(&self) -> Result<(), Error> {
+        if self.status.is_set(status::alert_fatal_fault) {
+            return Err(Error::FatalAlert);
+        }
+        Ok(())
+    }
 }
 
 impl AesRaw for AesRegisters {
@@ -123,19 +134,19 @@ impl AesRaw for AesRegisters {
         key_length: KeyLength,
         key_share0: &[u32; 8],
         key_share1: &[u32; 8],
-    ) {
-        while !self.status.is_set(status::idle) {}
+    ) -> Result<(), Error> {
+        self.check_fatal_fault()?;
+        platform::poll(|| self.status.is_set(status::idle))?;
 
         let ctrl_value = key_length.length() + operation.reg_val() + mode.reg_val();
-        self.ctrl_shadowed.write(ctrl_value);
-        self.ctrl_shadowed.write(ctrl_value);
+        self.ctrl_shadowed.write(ctrl_value)?;
 
         for i in 0..8 {
             self.key_share0[i].set(key_share0[i]);
             self.key_share1[i].set(key_share1[i]);
         }
 
-        while !self.status.is_set(status::idle) {}
+        platform::poll(|| self.status.is_set(status::idle))?;
 
         match mode {
             Mode::CBC { iv } | Mode::CTR { iv } => {
@@ -145,20 +156,25 @@ impl AesRaw for AesRegisters {
             }
             _ => (),
         }
+
+        Ok(())
     }
 
-    unsafe fn execute(&mut self, input: &[u32], output: &mut [u32]) {
+    unsafe fn execute(&mut self, input: &[u32], output: &mut [u32]) -> Result<(), Error> {
         let length = input.len();
-        assert!(length % 4 == 0);
+        if length % 4 != 0 || output.len() < length {
+            return Err(Error::InvalidArgument);
+        }
+        self.check_fatal_fault()?;
         let block_num = length / 4;
 
         for block_idx in 0..(block_num + 2) {
             if block_idx == 1 {
-                while !self.status.is_set(status::input_ready) {}
+                platform::poll(|| self.status.is_set(status::input_ready))?;
             }
 
             if block_idx > 1 {
-                while !self.status.is_set(status::output_valid) {}
+                platform::poll(|| self.status.is_set(status::output_valid))?;
 
                 for i in 0..4 {
                     output[(block_idx - 2) * 4 + i] = self.data_out[i].get();
@@ -171,56 +187,225 @@ impl AesRaw for AesRegisters {
                 }
             }
         }
+
+        Ok(())
     }
 
-    unsafe fn deinitialize(&self) {
+    unsafe fn deinitialize(&self) -> Result<(), Error> {
         self.ctrl_shadowed
-            .write(ctrl_shadowed::manual_operation::SET);
-        self.ctrl_shadowed
-            .write(ctrl_shadowed::manual_operation::SET);
+            .write(ctrl_shadowed::manual_operation::SET)?;
 
         self.trigger
             .write(trigger::key_iv_data_in_clear::SET + trigger::data_out_clear::SET);
 
-        while !self.status.is_set(status::idle) {}
+        platform::poll(|| self.status.is_set(status::idle))?;
+
+        Ok(())
     }
 }
 
-pub trait Aes {}
+/// Driver of the aes IP, owns the handle of the instance it was created from
+pub struct Aes<P: Peripheral<Ip = ip::Aes>> {
+    aes: P,
+}
+
+impl<P: Peripheral<Ip = ip::Aes>> Aes<P> {
+    pub fn new(aes: P) -> Self {
+        Aes { aes }
+    }
+
+    /// Returns the handle of the instance
+    pub fn free(self) -> P {
+        self.aes
+    }
+
+    /// Returns a pointer to the registers of the instance, for functionality the driver lacks
+    ///
+    /// The registers also implement [`AesRaw`]. Accesses through the pointer may interfere with
+    /// the operations of the driver.
+    pub fn registers(&self) -> *mut AesRegisters {
+        P::ADDRESS.ptr()
+    }
+
+    /// Returns `Err(Error::RecoverableAlert)` if the aes IP reported an update error of the
+    /// control register, `Err(Error::FatalAlert)` after a fatal fault & `Err(Error::Timeout)` if
+    /// it does not become idle
+    pub fn configure(
+        &mut self,
+        mode: Mode,
+        operation: Operation,
+        key_length: KeyLength,
+        key_share0: &[u32; 8],
+        key_share1: &[u32; 8],
+    ) -> Result<(), Error> {
+        unsafe {
+            (*self.registers()).configure(mode, operation, key_length, key_share0, key_share1)
+        }
+    }
 
-#[cfg(test)]
+    /// Returns `Err(Error::InvalidArgument)` if `input` is not a multiple of the block size or
+    /// `output` is shorter, `Err(Error::FatalAlert)` after a fatal fault & `Err(Error::Timeout)`
+    /// if a block is not processed in time
+    pub fn execute(&mut self, input: &[u32], output: &mut [u32]) -> Result<(), Error> {
+        unsafe { (*self.registers()).execute(input, output) }
+    }
+
+    /// Returns `Err(Error::RecoverableAlert)` if the aes IP reported an update error of the
+    /// control register, `Err(Error::Timeout)` if it does not become idle
+    pub fn deinitialize(&mut self) -> Result<(), Error> {
+        unsafe { (*self.registers()).deinitialize() }
+    }
+}
+
+#[cfg(all(test, target_os = "none"))]
 mod tests {
-    use crate::devices::aes::{self, AesRaw};
+    use crate::devices::{aes, peripherals};
 
     #[test_case]
     fn basic() {
+        // Tests run one after another, no other user of the aes IP is active
+        let mut aes = aes::Aes::new(unsafe { peripherals::Aes::steal() });
+
+        aes.configure(
+            aes::Mode::CTR { iv: [0, 0, 0, 0] },
+            aes::Operation::Encrypt,
+            aes::KeyLength::Aes256,
+            &[0, 0, 0, 0, 0, 0, 0, 0],
+            &[0, 0, 0, 0, 0, 0, 0, 0],
+        )
+        .expect("Control register update failed");
+
+        let plain = [32; 8];
+        let mut encrypted = [0; 8];
+        let mut decrypted = [0; 8];
+
+        aes.execute(&plain, &mut encrypted)
+            .expect("Encryption failed");
+        aes.deinitialize().expect("Control register update failed");
+        aes.configure(
+            aes::Mode::CTR { iv: [0, 0, 0, 0] },
+            aes::Operation::Decrypt,
+            aes::KeyLength::Aes256,
+            &[0, 0, 0, 0, 0, 0, 0, 0],
+            &[0, 0, 0, 0, 0, 0, 0, 0],
+        )
+        .expect("Control register update failed");
+        aes.execute(&encrypted, &mut decrypted)
+            .expect("Decryption failed");
+
+        assert_eq!(plain, decrypted);
+    }
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod host_tests {
+    use super::*;
+    use crate::{devices::alerts::AlertClass, mock::MockRegisters};
+
+    #[test]
+    fn configure() {
+        let aes = MockRegisters::<AesRegisters>::new();
+        aes.set_after(&aes.status, status::idle, 2);
+
+        let mode = Mode::CBC { iv: [1, 2, 3, 4] };
+        let ctrl =
+            (KeyLength::Aes128.length() + Operation::Encrypt.reg_val() + mode.reg_val()).value;
         unsafe {
-            let aes_mod = aes::get_aes_raw();
-
-            (*aes_mod).configure(
-                aes::Mode::CTR { iv: [0, 0, 0, 0] },
-                aes::Operation::Encrypt,
-                aes::KeyLength::Aes256,
-                &[0, 0, 0, 0, 0, 0, 0, 0],
-                &[0, 0, 0, 0, 0, 0, 0, 0],
-            );
-
-            let plain = [32; 8];
-            let mut encrypted = [0; 8];
-            let mut decrypted = [0; 8];
-
-            (*aes_mod).execute(&plain, &mut encrypted);
-            (*aes_mod).deinitialize();
-            (*aes_mod).configure(
-                aes::Mode::CTR { iv: [0, 0, 0, 0] },
-                aes::Operation::Decrypt,
-                aes::KeyLength::Aes256,
-                &[0, 0, 0, 0, 0, 0, 0, 0],
-                &[0, 0, 0, 0, 0, 0, 0, 0],
-            );
-            (*aes_mod).execute(&encrypted, &mut decrypted);
-
-            assert_eq!(plain, decrypted);
+            (*aes.ptr())
+                .configure(
+                    mode,
+                    Operation::Encrypt,
+                    KeyLength::Aes128,
+                    &[5; 8],
+                    &[6; 8],
+                )
+                .unwrap();
+        }
+
+        // Shadowed registers are written twice
+        assert_eq!(aes.writes(&aes.ctrl_shadowed), [ctrl, ctrl]);
+        for i in 0..8 {
+            assert_eq!(aes.writes(&aes.key_share0[i]), [5]);
+            assert_eq!(aes.writes(&aes.key_share1[i]), [6]);
+        }
+        let iv = aes.iv.iter().map(|iv| aes.writes(iv)).collect::<Vec<_>>();
+        assert_eq!(iv, [[1], [2], [3], [4]]);
+    }
+
+    #[test]
+    fn configure_update_error() {
+        let aes = MockRegisters::<AesRegisters>::new();
+        aes.set_after(&aes.status, status::idle, 0);
+        aes.set_after(&aes.status, status::alert_recov_ctrl_update_err, 0);
+
+        let result = unsafe {
+            (*aes.ptr()).configure(
+                Mode::ECB,
+                Operation::Decrypt,
+                KeyLength::Aes256,
+                &[0; 8],
+                &[0; 8],
+            )
+        };
+        assert_eq!(result, Err(Error::RecoverableAlert));
+    }
+
+    #[test]
+    fn execute() {
+        let aes = MockRegisters::<AesRegisters>::new();
+        aes.set_after(&aes.status, status::input_ready, 1);
+        aes.set_after(&aes.status, status::output_valid, 1);
+        for (i, data_out) in aes.data_out.iter().enumerate() {
+            aes.push_reads(data_out, [0x10 + i as u32, 0x20 + i as u32]);
+        }
+
+        let input = [1, 2, 3, 4, 5, 6, 7, 8];
+        let mut output = [0; 8];
+        unsafe { (*aes.ptr()).execute(&input, &mut output).unwrap() };
+
+        assert_eq!(output, [0x10, 0x11, 0x12, 0x13, 0x20, 0x21, 0x22, 0x23]);
+        for i in 0..4 {
+            assert_eq!(aes.writes(&aes.data_in[i]), [input[i], input[4 + i]]);
         }
     }
+
+    #[test]
+    fn execute_errors() {
+        let aes = MockRegisters::<AesRegisters>::new();
+        let mut output = [0; 4];
+        let result = unsafe { (*aes.ptr()).execute(&[1, 2, 3], &mut output) };
+        assert_eq!(result, Err(Error::InvalidArgument));
+        let result = unsafe { (*aes.ptr()).execute(&[0; 8], &mut output) };
+        assert_eq!(result, Err(Error::InvalidArgument));
+
+        aes.set(&aes.status, status::alert_fatal_fault::SET.value);
+        let result = unsafe { (*aes.ptr()).execute(&[0; 4], &mut output) };
+        assert_eq!(result, Err(Error::FatalAlert));
+        assert!(aes.writes(&aes.data_in[0]).is_empty());
+    }
+
+    #[test]
+    fn deinitialize() {
+        let aes = MockRegisters::<AesRegisters>::new();
+        aes.set_after(&aes.status, status::idle, 3);
+
+        unsafe { (*aes.ptr()).deinitialize().unwrap() };
+
+        let ctrl = ctrl_shadowed::manual_operation::SET.value;
+        assert_eq!(aes.writes(&aes.ctrl_shadowed), [ctrl, ctrl]);
+        let trigger = trigger::key_iv_data_in_clear::SET + trigger::data_out_clear::SET;
+        assert_eq!(aes.writes(&aes.trigger), [trigger.value]);
+    }
+
+    #[test]
+    fn alerts() {
+        let aes = MockRegisters::<AesRegisters>::new();
+        aes.trigger_test(Alert::FatalFault);
+        aes.trigger_test(Alert::RecovCtrlUpdateErr);
+
+        assert_eq!(aes.writes(&aes.alert_test), [0b10, 0b01]);
+        assert_eq!(Alert::FatalFault.class(), AlertClass::Fatal);
+        assert_eq!(Alert::RecovCtrlUpdateErr.class(), AlertClass::Recoverable);
+        assert_eq!(Alert::try_from(1), Ok(Alert::FatalFault));
+    }
 }
@@ -0,0 +1,39 @@
+//! Alert tests of all IPs of the top
+//!
+//! Every IP can raise each of its alerts once through its alert test register, eg. to bring up
+//! the alert handler & to test its classification & escalation. The alerts are numbered like the
+//! inputs of the alert handler by [`AlertId`], the register block of a single IP also offers the
+//! typed `trigger_test(Alert)` method for its own alerts.
+
+use super::AlertId;
+use crate::registers::WriteOnly;
+use tock_registers::interfaces::Writeable;
+
+/// Severity of an alert, taken from its name in the IP description
+///
+/// Alerts named `recov_*` are recoverable, all others are fatal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertClass {
+    /// The IP stays in an error state until reset once the alert was raised
+    Fatal,
+    /// The alert is raised once per event
+    Recoverable,
+}
+
+/// Raises `alert` once through the alert test register of its IP
+///
+/// # Safety
+/// Depending on the configuration of the alert handler the alert may escalate, eg. reset the
+/// chip.
+pub unsafe fn trigger_test(alert: AlertId) {
+    let (address, mask) = alert.test_register();
+    let register = unsafe { &*(address as *const WriteOnly<u32>) };
+    register.set(mask);
+}
+
+/// Returns all alerts of the top with the class `class`
+pub fn alerts(class: AlertClass) -> impl Iterator<Item = AlertId> {
+    AlertId::ALL
+        .into_iter()
+        .filter(move |alert| alert.class() == class)
+}
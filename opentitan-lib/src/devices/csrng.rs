@@ -3,98 +3,151 @@
 //! TODO:
 //!     - make functions on CsrngRegisters unsafe by default
 //!     - implementation for CsrngRaw
-//!     - implementation for Csrng
 
-use super::addresses;
-use opentitan_macros::registers;
+use super::{
+    peripherals::{self, ip},
+    platform, Peripheral,
+};
+use crate::registers::TryWriteable;
+use crate::Error;
 use tock_registers::interfaces::{Readable, Writeable};
 
-#[registers("hw/ip/csrng/data/csrng.hjson")]
+#[cfg(not(feature = "pregenerated"))]
+#[opentitan_macros::registers("hw/ip/csrng/data/csrng.hjson")]
 pub struct CsrngRegisters;
-
-const CSRNG: *mut CsrngRegisters = addresses::CSRNG as *mut CsrngRegisters;
+#[cfg(feature = "pregenerated")]
+include!("../generated/devices/csrng.rs");
 
 /// Returns a pointer to the registers of the csrng IP
 ///
-/// This should only be used if either [`CsrngRaw`] or [`Csrng`] do not meet the
-/// requirements (eg. performance or functionality)
-///
 /// # Safety
-/// Reading and modifying the csrng registers may have potential side effects.
-/// Usage of the returned pointer is therefore inherently unsafe.
+/// The csrng IP must not be used through its handle at the same time.
+#[deprecated(
+    note = "create a `Csrng` from the handle of `Peripherals::take`, see `Csrng::registers`"
+)]
 pub unsafe fn get_csrng_registers() -> *mut CsrngRegisters {
-    CSRNG
+    peripherals::Csrng::ADDRESS.ptr()
 }
 
-/// Returns a pointer to the csrng IP
-///
-/// This should only be used if [`Csrng`] does not meet the
-/// requirements (eg. performance or functionality)
-///
-/// The returned value is an unsafe wrapper for the [`CsrngRegisters`] struct
-/// that implements a set of commonly used functionality.
+/// Returns a pointer to the csrng IP, an unsafe wrapper that implements commonly used functionality
 ///
 /// # Safety
-/// Reading and modifying the csrng registers may have potential side effects.
-/// Usage of the returned pointer is therefore inherently unsafe.
+/// The csrng IP must not be used through its handle at the same time.
+#[deprecated(
+    note = "create a `Csrng` from the handle of `Peripherals::take`, see `Csrng::registers`"
+)]
 pub unsafe fn get_csrng_raw() -> *mut impl CsrngRaw {
-    CSRNG
+    peripherals::Csrng::ADDRESS.ptr::<CsrngRegisters>()
 }
 
+/// Maximum length of a seed in words
+const SEED_WORDS: usize = 12;
+
 pub trait CsrngRaw {
-    unsafe fn configure(&mut self, seed: Option<&[u32]>);
+    /// Returns `Err(Error::Locked)` if the configuration is locked by the regwen register,
+    /// `Err(Error::InvalidArgument)` for seeds longer than 12 words & `Err(Error::Timeout)` if the
+    /// csrng IP does not accept a command
+    unsafe fn configure(&mut self, seed: Option<&[u32]>) -> Result<(), Error>;
 
-    unsafe fn generate(&mut self, data: &mut [u32; 4]);
+    /// Returns `Err(Error::Status)` if the csrng IP reported an error for a command,
+    /// `Err(Error::Timeout)` if it does not generate the bits in time
+    unsafe fn generate(&mut self, data: &mut [u32; 4]) -> Result<(), Error>;
 }
 
 impl CsrngRegisters {
-    unsafe fn _send_cmd_data(&mut self, data: u32) {
-        while !self.sw_cmd_sts.is_set(sw_cmd_sts::cmd_rdy) {}
+    /// Returns `Err(Error::Status)` if the previous command failed, `Err(Error::Timeout)` if the
+    /// csrng IP is not ready for the command
+    unsafe fn _send_cmd_data(&mut self, data: u32) -> Result<(), Error> {
+        platform::poll(|| self.sw_cmd_sts.is_set(sw_cmd_sts::cmd_rdy))?;
+        if self.sw_cmd_sts.is_set(sw_cmd_sts::cmd_sts) {
+            return Err(Error::Status("csrng command failed"));
+        }
         self.cmd_req.set(data);
+        Ok(())
     }
 }
 
 impl CsrngRaw for CsrngRegisters {
-    unsafe fn configure(&mut self, seed: Option<&[u32]>) {
+    unsafe fn configure(&mut self, seed: Option<&[u32]>) -> Result<(), Error> {
+        if seed.is_some_and(|seed| seed.len() > SEED_WORDS) {
+            return Err(Error::InvalidArgument);
+        }
+
         unsafe {
-            self.ctrl.write(ctrl::enable::SET);
-            self.hw_exc_sts.set(0);
+            self.ctrl.try_write(ctrl::enable::SET)?;
+            self.hw_exc_sts.clear_all();
 
             let header = generate_header(CsrngCMD::Uninstantiate, 0, 0, 0);
-            self._send_cmd_data(header);
+            self._send_cmd_data(header)?;
 
             if let Some(seed) = seed {
-                let seed_len = seed.len();
-                let seed_len = if seed_len < 12 { seed_len } else { 12 };
+                let header = generate_header(CsrngCMD::Instantiate, seed.len() as u32, 1 << 8, 0);
+                self._send_cmd_data(header)?;
 
-                let header = generate_header(CsrngCMD::Instantiate, seed_len as u32, 1 << 8, 0);
-                self._send_cmd_data(header);
-
-                for value in &seed[0..seed_len] {
-                    self._send_cmd_data(*value);
+                for value in seed {
+                    self._send_cmd_data(*value)?;
                 }
             } else {
                 let header = generate_header(CsrngCMD::Instantiate, 0, 0, 0);
-                self._send_cmd_data(header);
+                self._send_cmd_data(header)?;
             }
         }
+
+        Ok(())
     }
 
-    unsafe fn generate(&mut self, data: &mut [u32; 4]) {
+    unsafe fn generate(&mut self, data: &mut [u32; 4]) -> Result<(), Error> {
         unsafe {
             let header = generate_header(CsrngCMD::Generate, 0, 0, 1);
-            self._send_cmd_data(header);
+            self._send_cmd_data(header)?;
 
-            while !self.genbits_vld.is_set(genbits_vld::genbits_vld) {}
+            platform::poll(|| self.genbits_vld.is_set(genbits_vld::genbits_vld))?;
 
             for val in data {
                 *val = self.genbits.get();
             }
         }
+
+        Ok(())
     }
 }
 
-pub trait Csrng {}
+/// Driver of the csrng IP, owns the handle of the instance it was created from
+pub struct Csrng<P: Peripheral<Ip = ip::Csrng>> {
+    csrng: P,
+}
+
+impl<P: Peripheral<Ip = ip::Csrng>> Csrng<P> {
+    pub fn new(csrng: P) -> Self {
+        Csrng { csrng }
+    }
+
+    /// Returns the handle of the instance
+    pub fn free(self) -> P {
+        self.csrng
+    }
+
+    /// Returns a pointer to the registers of the instance, for functionality the driver lacks
+    ///
+    /// The registers also implement [`CsrngRaw`]. Accesses through the pointer may interfere with
+    /// the operations of the driver.
+    pub fn registers(&self) -> *mut CsrngRegisters {
+        P::ADDRESS.ptr()
+    }
+
+    /// Returns `Err(Error::Locked)` if the configuration is locked by the regwen register,
+    /// `Err(Error::InvalidArgument)` for seeds longer than 12 words & `Err(Error::Timeout)` if the
+    /// csrng IP does not accept a command
+    pub fn configure(&mut self, seed: Option<&[u32]>) -> Result<(), Error> {
+        unsafe { (*self.registers()).configure(seed) }
+    }
+
+    /// Returns `Err(Error::Status)` if the csrng IP reported an error for a command,
+    /// `Err(Error::Timeout)` if it does not generate the bits in time
+    pub fn generate(&mut self, data: &mut [u32; 4]) -> Result<(), Error> {
+        unsafe { (*self.registers()).generate(data) }
+    }
+}
 
 #[derive(Copy, Clone)]
 #[allow(dead_code)]
@@ -121,7 +174,7 @@ unsafe fn generate_header(acmd: CsrngCMD, clen: u32, flags: u32, glen: u32) -> u
     acmd as u32 | (clen & 0b1111) << 4 | flags | (glen & 0b1111_1111_1111) << 12
 }
 
-#[cfg(test)]
+#[cfg(all(test, target_os = "none"))]
 mod tests {
     use crate::mark_test_as_skipped;
 
@@ -134,3 +187,73 @@ mod tests {
         mark_test_as_skipped!()
     }
 }
+
+#[cfg(all(test, not(target_os = "none")))]
+mod host_tests {
+    use super::*;
+    use crate::mock::MockRegisters;
+
+    #[test]
+    fn configure() {
+        let csrng = MockRegisters::<CsrngRegisters>::new();
+        csrng.set(&csrng.regwen, CsrngRegisters::REGWEN_RESVAL);
+        csrng.set_after(&csrng.sw_cmd_sts, sw_cmd_sts::cmd_rdy, 2);
+
+        unsafe { (*csrng.ptr()).configure(Some(&[0xaa, 0xbb])).unwrap() };
+
+        assert_eq!(csrng.writes(&csrng.ctrl), [ctrl::enable::SET.value]);
+        let uninstantiate = CsrngCMD::Uninstantiate as u32;
+        let instantiate = CsrngCMD::Instantiate as u32 | 2 << 4 | 1 << 8;
+        assert_eq!(
+            csrng.writes(&csrng.cmd_req),
+            [uninstantiate, instantiate, 0xaa, 0xbb]
+        );
+    }
+
+    #[test]
+    fn configure_locked() {
+        let csrng = MockRegisters::<CsrngRegisters>::new();
+        csrng.set_after(&csrng.sw_cmd_sts, sw_cmd_sts::cmd_rdy, 0);
+
+        let result = unsafe { (*csrng.ptr()).configure(None) };
+        assert_eq!(result, Err(Error::Locked));
+        assert!(csrng.writes(&csrng.cmd_req).is_empty());
+    }
+
+    #[test]
+    fn configure_errors() {
+        let csrng = MockRegisters::<CsrngRegisters>::new();
+        csrng.set(&csrng.regwen, CsrngRegisters::REGWEN_RESVAL);
+        let result = unsafe { (*csrng.ptr()).configure(Some(&[0; 13])) };
+        assert_eq!(result, Err(Error::InvalidArgument));
+        assert!(csrng.writes(&csrng.ctrl).is_empty());
+
+        // The uninstantiate command fails
+        csrng.set_after(&csrng.sw_cmd_sts, sw_cmd_sts::cmd_rdy, 0);
+        csrng.set_after(&csrng.sw_cmd_sts, sw_cmd_sts::cmd_sts, 3);
+        let result = unsafe { (*csrng.ptr()).configure(None) };
+        assert_eq!(result, Err(Error::Status("csrng command failed")));
+        assert_eq!(
+            csrng.writes(&csrng.cmd_req),
+            [CsrngCMD::Uninstantiate as u32]
+        );
+    }
+
+    #[test]
+    fn generate() {
+        let csrng = MockRegisters::<CsrngRegisters>::new();
+        csrng.set_after(&csrng.sw_cmd_sts, sw_cmd_sts::cmd_rdy, 0);
+        csrng.set_after(&csrng.genbits_vld, genbits_vld::genbits_vld, 4);
+        csrng.push_reads(&csrng.genbits, [1, 2, 3, 4]);
+
+        let mut data = [0; 4];
+        unsafe { (*csrng.ptr()).generate(&mut data).unwrap() };
+
+        assert_eq!(data, [1, 2, 3, 4]);
+        assert_eq!(
+            csrng.writes(&csrng.cmd_req),
+            [CsrngCMD::Generate as u32 | 1 << 12]
+        );
+        assert_eq!(csrng.reads(&csrng.genbits_vld), 5);
+    }
+}
@@ -3,93 +3,191 @@
 //! TODO:
 //!     - make functions on HmacRegisters unsafe by default
 //!     - implementation for HmacRaw
-//!     - implementation for Hmac
 
-use super::addresses;
-use opentitan_macros::registers;
+use super::{
+    peripherals::{self, ip},
+    platform, Peripheral,
+};
+use crate::registers::Interrupts;
+use crate::Error;
 use tock_registers::interfaces::{Readable, Writeable};
 
-#[registers("hw/ip/hmac/data/hmac.hjson")]
+#[cfg(not(feature = "pregenerated"))]
+#[opentitan_macros::registers("hw/ip/hmac/data/hmac.hjson")]
 pub struct HmacRegisters;
-
-const HMAC: *mut HmacRegisters = addresses::HMAC as *mut HmacRegisters;
+#[cfg(feature = "pregenerated")]
+include!("../generated/devices/hmac.rs");
 
 /// Returns a pointer to the registers of the hmac IP
 ///
-/// This should only be used if either [`HmacRaw`] or [`Hmac`] do not meet the
-/// requirements (eg. performance or functionality)
-///
 /// # Safety
-/// Reading and modifying the hmac registers may have potential side effects.
-/// Usage of the returned pointer is therefore inherently unsafe.
+/// The hmac IP must not be used through its handle at the same time.
+#[deprecated(
+    note = "create a `Hmac` from the handle of `Peripherals::take`, see `Hmac::registers`"
+)]
 pub unsafe fn get_hmac_registers() -> *mut HmacRegisters {
-    HMAC
+    peripherals::Hmac::ADDRESS.ptr()
 }
 
-/// Returns a pointer to the hmac IP
-///
-/// This should only be used if [`Hmac`] does not meet the
-/// requirements (eg. performance or functionality)
-///
-/// The returned value is an unsafe wrapper for the [`HmacRegisters`] struct
-/// that implements a set of commonly used functionality.
+/// Returns a pointer to the hmac IP, an unsafe wrapper that implements commonly used functionality
 ///
 /// # Safety
-/// Reading and modifying the hmac registers may have potential side effects.
-/// Usage of the returned pointer is therefore inherently unsafe.
+/// The hmac IP must not be used through its handle at the same time.
+#[deprecated(
+    note = "create a `Hmac` from the handle of `Peripherals::take`, see `Hmac::registers`"
+)]
 pub unsafe fn get_hmac_raw() -> *mut impl HmacRaw {
-    HMAC
+    peripherals::Hmac::ADDRESS.ptr::<HmacRegisters>()
 }
 
 pub trait HmacRaw {
-    unsafe fn hash_data(&mut self, data: &[u32], digest: &mut [u32; 8]);
+    /// Returns `Err(Error::Timeout)` if the hmac IP does not finish the hash in time
+    unsafe fn hash_data(
+        &mut self,
+        data: &[u32],
+        digest: &mut [u32; params::NUM_WORDS],
+    ) -> Result<(), Error>;
 }
 
 impl HmacRaw for HmacRegisters {
-    unsafe fn hash_data(&mut self, data: &[u32], digest: &mut [u32; 8]) {
+    unsafe fn hash_data(
+        &mut self,
+        data: &[u32],
+        digest: &mut [u32; params::NUM_WORDS],
+    ) -> Result<(), Error> {
         self.cfg.write(cfg::sha_en::SET);
         self.cmd.write(cmd::hash_start::SET);
 
-        for val in data {
-            while self.status.is_set(status::fifo_full) {}
-
-            // TODO: use full msg_fifo size
-            self.msg_fifo[0].set(*val);
+        // Writes to a full fifo are stalled by the interconnect until there is space
+        for chunk in data.chunks(self.msg_fifo.words()) {
+            self.msg_fifo.copy_from_slice(0, chunk)?;
         }
 
         self.cmd.write(cmd::hash_process::SET);
 
-        while !self.intr_state.is_set(intr::hmac_done) {}
+        platform::poll(|| self.is_pending(Interrupt::HmacDone))?;
+        // hmac_done is an event interrupt, which can always be acknowledged
+        let _ = self.acknowledge(Interrupt::HmacDone);
 
-        for i in 0..8 {
-            digest[i] = self.digest[i].get();
+        for (out, reg) in digest.iter_mut().zip(self.digest.iter()) {
+            *out = reg.get();
         }
+
+        Ok(())
     }
 }
 
-pub trait Hmac {}
+/// Driver of the hmac IP, owns the handle of the instance it was created from
+pub struct Hmac<P: Peripheral<Ip = ip::Hmac>> {
+    hmac: P,
+}
+
+impl<P: Peripheral<Ip = ip::Hmac>> Hmac<P> {
+    pub fn new(hmac: P) -> Self {
+        Hmac { hmac }
+    }
+
+    /// Returns the handle of the instance
+    pub fn free(self) -> P {
+        self.hmac
+    }
 
-#[cfg(test)]
+    /// Returns a pointer to the registers of the instance, for functionality the driver lacks
+    ///
+    /// The registers also implement [`HmacRaw`]. Accesses through the pointer may interfere with
+    /// the operations of the driver.
+    pub fn registers(&self) -> *mut HmacRegisters {
+        P::ADDRESS.ptr()
+    }
+
+    /// Returns `Err(Error::Timeout)` if the hmac IP does not finish the hash in time
+    pub fn hash_data(
+        &mut self,
+        data: &[u32],
+        digest: &mut [u32; params::NUM_WORDS],
+    ) -> Result<(), Error> {
+        unsafe { (*self.registers()).hash_data(data, digest) }
+    }
+}
+
+#[cfg(all(test, target_os = "none"))]
 mod tests {
     use super::*;
 
     #[test_case]
     fn basic() {
-        unsafe {
-            let hmac = get_hmac_raw();
+        // Tests run one after another, no other user of the hmac IP is active
+        let mut hmac = Hmac::new(unsafe { peripherals::Hmac::steal() });
 
-            let data = [32u32; 32];
-            let mut digest = [0u32; 8];
+        let data = [32u32; 32];
+        let mut digest = [0u32; params::NUM_WORDS];
 
-            (*hmac).hash_data(&data, &mut digest);
+        hmac.hash_data(&data, &mut digest).expect("Hashing failed");
 
-            assert_eq!(
-                digest,
-                [
-                    1226795820, 575703230, 291893938, 2935539018, 2827460678, 30448964, 4171743692,
-                    4048342112
-                ]
-            );
-        }
+        assert_eq!(
+            digest,
+            [
+                1226795820, 575703230, 291893938, 2935539018, 2827460678, 30448964, 4171743692,
+                4048342112
+            ]
+        );
+    }
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod host_tests {
+    use super::*;
+    use crate::mock::MockRegisters;
+
+    #[test]
+    fn hash_data() {
+        let hmac = MockRegisters::<HmacRegisters>::new();
+        hmac.set_after(&hmac.intr_state, intr::hmac_done, 3);
+        hmac.push_reads(&hmac.digest[0], [0xaa]);
+
+        let mut digest = [0; params::NUM_WORDS];
+        assert_eq!(
+            unsafe { (*hmac.ptr()).hash_data(&[1, 2, 3], &mut digest) },
+            Ok(())
+        );
+
+        assert_eq!(digest[0], 0xaa);
+        assert_eq!(hmac.reads(&hmac.intr_state), 4);
+        assert_eq!(hmac.writes(&hmac.intr_state), [intr::hmac_done::SET.value]);
+    }
+
+    #[test]
+    fn hash_data_timeout() {
+        let hmac = MockRegisters::<HmacRegisters>::new();
+        let mut digest = [0; params::NUM_WORDS];
+        assert_eq!(
+            unsafe { (*hmac.ptr()).hash_data(&[1, 2, 3], &mut digest) },
+            Err(Error::Timeout)
+        );
+    }
+
+    #[test]
+    fn interrupts() {
+        let hmac = MockRegisters::<HmacRegisters>::new();
+        hmac.enable(Interrupt::HmacErr);
+        hmac.enable(Interrupt::HmacDone);
+        hmac.disable(Interrupt::HmacErr);
+        hmac.force(Interrupt::FifoEmpty);
+
+        assert!(hmac.is_enabled(Interrupt::HmacDone));
+        assert!(!hmac.is_enabled(Interrupt::HmacErr));
+        assert_eq!(hmac.writes(&hmac.intr_enable), [0b100, 0b101, 0b001]);
+        assert_eq!(hmac.writes(&hmac.intr_test), [0b010]);
+
+        hmac.set(&hmac.intr_state, 0b010);
+        assert!(hmac.is_pending(Interrupt::FifoEmpty));
+        assert!(!hmac.is_pending(Interrupt::HmacDone));
+    }
+
+    #[test]
+    fn acknowledge() {
+        let hmac = MockRegisters::<HmacRegisters>::new();
+        assert_eq!(hmac.acknowledge(Interrupt::HmacErr), Ok(()));
+        assert_eq!(hmac.writes(&hmac.intr_state), [0b100]);
     }
 }
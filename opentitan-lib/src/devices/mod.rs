@@ -1,22 +1,125 @@
+//! Drivers & addresses of the IPs of the selected top
+//!
+//! Drivers are only compiled if the top contains their IP, see the `top_*` features of the lib.
+//! Using the driver of an IP the top lacks fails with an error naming the IP & the top.
+//! They are created from the handles of [`Peripherals::take`], so every IP instance is used by a
+//! single driver.
+
+#[cfg(opentitan_ip = "aes")]
 pub mod aes;
+pub mod alerts;
+#[cfg(opentitan_ip = "csrng")]
 pub mod csrng;
+#[cfg(opentitan_ip = "hmac")]
 pub mod hmac;
+#[cfg(opentitan_ip = "otbn")]
 pub mod otbn;
+pub mod platform;
+// Every top has a UART, the lib prints through uart0
 pub mod uart;
+// Stubs of the drivers whose IP is not in the top, see `build.rs`
+include!(concat!(env!("OUT_DIR"), "/missing_drivers.rs"));
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Address range of a register interface or memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Address {
+    pub base: usize,
+    /// Size in bytes
+    pub size: usize,
+}
 
-use opentitan_macros::addresses;
+impl Address {
+    pub const fn new(base: usize, size: usize) -> Self {
+        Address { base, size }
+    }
 
-addresses!("hw/top_earlgrey/data/top_earlgrey.hjson");
+    /// Returns the base address as a pointer to the registers or memory
+    pub const fn ptr<T>(&self) -> *mut T {
+        self.base as *mut T
+    }
 
-/// Verilator platform constants
+    /// Returns the first address after the range
+    pub const fn end(&self) -> usize {
+        self.base + self.size
+    }
+
+    pub const fn contains(&self, addr: usize) -> bool {
+        addr >= self.base && addr < self.end()
+    }
+}
+
+/// Owned, zero sized handle of an IP instance
+///
+/// The handles are generated by `addresses!` & handed out once by [`Peripherals::take`]. Drivers
+/// consume the handle of the instance they use.
 ///
-/// TODO: allow target platform selection using features
-pub mod platform {
-    pub const CPU_FREQ: usize = 500_000;
-    pub const HIGH_SPEED_PERIPHERAL_FREQ: usize = 500_000;
-    pub const PERIPHERAL_FREQ: usize = 125_000;
-    pub const USB_FREQ: usize = 500_000;
-    pub const AON_FREQ: usize = 125_000;
-
-    pub const UART_BAUD_RATE: u32 = 7200;
+/// # Safety
+/// Only one handle of an instance may exist, unless it is stolen.
+pub unsafe trait Peripheral {
+    /// Marker of the type of the IP, eg. `peripherals::ip::Uart`
+    type Ip;
+    /// Name of the instance in the top, eg. `uart0`
+    const NAME: &'static str;
+    /// Registers of the instance, its first memory if it has none
+    const ADDRESS: Address;
+}
+
+/// Set once the peripherals are handed out
+static PERIPHERALS_TAKEN: AtomicBool = AtomicBool::new(false);
+
+impl Peripherals {
+    /// Returns the handles of all IP instances the first time it is called, `None` afterwards
+    ///
+    /// The handle of uart0 is `None` while stdout owns it, [`print!`](crate::print) prints through
+    /// uart0 from the start. It is handed out after taking the uart back with
+    /// [`take_stdout`](crate::print::take_stdout). Panics are printed to uart0 in any case.
+    pub fn take() -> Option<Self> {
+        let take = || {
+            if PERIPHERALS_TAKEN.load(Ordering::Relaxed) {
+                None
+            } else {
+                PERIPHERALS_TAKEN.store(true, Ordering::Relaxed);
+                let mut peripherals = unsafe { Peripherals::steal() };
+                if crate::print::owns_uart0() {
+                    peripherals.uart0 = None;
+                }
+                Some(peripherals)
+            }
+        };
+        // Opentitan has no atomic read-modify-write, the check & set must not be interrupted
+        #[cfg(target_os = "none")]
+        return riscv::interrupt::free(take);
+        #[cfg(not(target_os = "none"))]
+        take()
+    }
+}
+
+#[cfg(all(not(feature = "pregenerated"), opentitan_top = "earlgrey"))]
+opentitan_macros::addresses!("hw/top_earlgrey/data/top_earlgrey.hjson");
+#[cfg(all(not(feature = "pregenerated"), opentitan_top = "englishbreakfast"))]
+opentitan_macros::addresses!("hw/top_englishbreakfast/data/top_englishbreakfast.hjson");
+#[cfg(all(not(feature = "pregenerated"), opentitan_top = "darjeeling"))]
+opentitan_macros::addresses!("hw/top_darjeeling/data/top_darjeeling.hjson");
+#[cfg(feature = "pregenerated")]
+include!("../generated/devices/mod.rs");
+
+#[cfg(all(test, not(target_os = "none")))]
+mod host_tests {
+    use super::*;
+
+    #[test]
+    fn take_peripherals() {
+        let peripherals = Peripherals::take().expect("peripherals taken before");
+        assert!(Peripherals::take().is_none());
+
+        fn address<P: Peripheral>(_: &P) -> (&'static str, Address) {
+            (P::NAME, P::ADDRESS)
+        }
+        assert_eq!(address(&peripherals.uart1), ("uart1", addresses::UART1));
+        assert_eq!(core::mem::size_of::<peripherals::Uart1>(), 0);
+        // Stdout is only set up on the target
+        assert!(peripherals.uart0.is_some());
+    }
 }
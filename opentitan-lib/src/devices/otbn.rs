@@ -5,39 +5,30 @@
 //!     - implementation for OtbnRaw
 //!     - implementation for Otbn
 
-use super::addresses;
-use opentitan_macros::registers;
+use super::{peripherals, Peripheral};
 
-#[registers("hw/ip/otbn/data/otbn.hjson")]
+#[cfg(not(feature = "pregenerated"))]
+#[opentitan_macros::registers("hw/ip/otbn/data/otbn.hjson")]
 pub struct OtbnRegisters;
-
-const OTBN: *const OtbnRegisters = addresses::OTBN as *const OtbnRegisters;
+#[cfg(feature = "pregenerated")]
+include!("../generated/devices/otbn.rs");
 
 /// Returns a pointer to the registers of the otbn IP
 ///
-/// This should only be used if either [`OtbnRaw`] or [`Otbn`] do not meet the
-/// requirements (eg. performance or functionality)
-///
 /// # Safety
-/// Reading and modifying the otbn registers may have potential side effects.
-/// Usage of the returned pointer is therefore inherently unsafe.
+/// The otbn IP must not be used through its handle at the same time.
+#[deprecated(note = "use the handle of `Peripherals::take`, see `peripherals::Otbn`")]
 pub unsafe fn get_otbn_registers() -> *const OtbnRegisters {
-    OTBN
+    peripherals::Otbn::ADDRESS.ptr()
 }
 
-/// Returns a pointer to the otbn IP
-///
-/// This should only be used if [`Otbn`] does not meet the
-/// requirements (eg. performance or functionality)
-///
-/// The returned value is an unsafe wrapper for the [`OtbnRegisters`] struct
-/// that implements a set of commonly used functionality.
+/// Returns a pointer to the otbn IP, an unsafe wrapper that implements commonly used functionality
 ///
 /// # Safety
-/// Reading and modifying the otbn registers may have potential side effects.
-/// Usage of the returned pointer is therefore inherently unsafe.
+/// The otbn IP must not be used through its handle at the same time.
+#[deprecated(note = "use the handle of `Peripherals::take`, see `peripherals::Otbn`")]
 pub unsafe fn get_otbn_raw() -> *const impl OtbnRaw {
-    OTBN
+    peripherals::Otbn::ADDRESS.ptr::<OtbnRegisters>()
 }
 
 pub trait OtbnRaw {}
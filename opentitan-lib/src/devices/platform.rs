@@ -0,0 +1,193 @@
+//! Clocks & timing of the platforms the lib runs on
+//!
+//! The platform is chosen with the `platform_*` features of the lib, eg. `platform_cw310`, and
+//! defaults to Verilator. Drivers take their clocks & baud rate from the selected profile, so an
+//! application runs on every platform without changes; it can query the platform with
+//! [`Platform::current`] where its behaviour has to differ, eg. to shorten a test in simulation.
+//!
+//! Drivers wait for the hardware with [`poll`], which gives up after the poll timeout of the
+//! profile instead of hanging forever.
+
+#[cfg(all(test, not(target_os = "none")))]
+use crate::mock::cycles;
+use crate::Error;
+#[cfg(not(all(test, not(target_os = "none"))))]
+use riscv::register::mcycle::read64 as cycles;
+
+#[cfg(any(
+    all(feature = "platform_verilator", feature = "platform_cw310"),
+    all(feature = "platform_verilator", feature = "platform_cw340"),
+    all(feature = "platform_verilator", feature = "platform_sim_dv"),
+    all(feature = "platform_cw310", feature = "platform_cw340"),
+    all(feature = "platform_cw310", feature = "platform_sim_dv"),
+    all(feature = "platform_cw340", feature = "platform_sim_dv"),
+))]
+compile_error!("only one of the platform_* features can be enabled");
+
+/// Platform the chip is simulated or emulated on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    /// Verilator simulation of the top
+    Verilator,
+    /// ChipWhisperer CW310 FPGA board
+    Cw310,
+    /// ChipWhisperer CW340 FPGA board
+    Cw340,
+    /// Simulation of the DV testbench
+    SimDv,
+}
+
+/// Clocks, baud rate & timeouts of a platform
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Profile {
+    /// Frequencies of the clocks in Hz
+    pub cpu_freq: usize,
+    pub high_speed_peripheral_freq: usize,
+    pub peripheral_freq: usize,
+    pub usb_freq: usize,
+    pub aon_freq: usize,
+    /// Baud rate of the UARTs if none is given to `configure`
+    pub uart_baud_rate: u32,
+    /// Time in µs to wait for an IP to finish an operation before giving up
+    pub poll_timeout_us: u32,
+}
+
+impl Platform {
+    /// All supported platforms
+    pub const ALL: [Platform; 4] = [
+        Platform::Verilator,
+        Platform::Cw310,
+        Platform::Cw340,
+        Platform::SimDv,
+    ];
+
+    /// Returns the platform the lib was built for
+    pub const fn current() -> Platform {
+        if cfg!(feature = "platform_cw310") {
+            Platform::Cw310
+        } else if cfg!(feature = "platform_cw340") {
+            Platform::Cw340
+        } else if cfg!(feature = "platform_sim_dv") {
+            Platform::SimDv
+        } else {
+            Platform::Verilator
+        }
+    }
+
+    /// Returns the name of the platform like in the `platform_*` features
+    pub const fn name(self) -> &'static str {
+        match self {
+            Platform::Verilator => "verilator",
+            Platform::Cw310 => "cw310",
+            Platform::Cw340 => "cw340",
+            Platform::SimDv => "sim_dv",
+        }
+    }
+
+    /// Whether the platform is a simulation, which runs orders of magnitude slower than the chip
+    pub const fn is_simulation(self) -> bool {
+        matches!(self, Platform::Verilator | Platform::SimDv)
+    }
+
+    /// Returns the clocks, baud rate & timeouts of the platform
+    pub const fn profile(self) -> Profile {
+        match self {
+            Platform::Verilator => Profile {
+                cpu_freq: 500_000,
+                high_speed_peripheral_freq: 500_000,
+                peripheral_freq: 125_000,
+                usb_freq: 500_000,
+                aon_freq: 125_000,
+                uart_baud_rate: 7200,
+                poll_timeout_us: 2_000_000,
+            },
+            // Both boards run the same bitstream clocks
+            Platform::Cw310 | Platform::Cw340 => Profile {
+                cpu_freq: 24_000_000,
+                high_speed_peripheral_freq: 24_000_000,
+                peripheral_freq: 6_000_000,
+                usb_freq: 48_000_000,
+                aon_freq: 250_000,
+                uart_baud_rate: 115_200,
+                poll_timeout_us: 100_000,
+            },
+            Platform::SimDv => Profile {
+                cpu_freq: 100_000_000,
+                high_speed_peripheral_freq: 96_000_000,
+                peripheral_freq: 24_000_000,
+                usb_freq: 48_000_000,
+                aon_freq: 200_000,
+                uart_baud_rate: 1_000_000,
+                poll_timeout_us: 10_000,
+            },
+        }
+    }
+}
+
+impl Profile {
+    /// Returns [`Profile::poll_timeout_us`] in cycles of the CPU
+    pub const fn poll_timeout_cycles(&self) -> u64 {
+        self.poll_timeout_us as u64 * self.cpu_freq as u64 / 1_000_000
+    }
+}
+
+/// Profile of the platform the lib was built for
+pub const PROFILE: Profile = Platform::current().profile();
+
+pub const CPU_FREQ: usize = PROFILE.cpu_freq;
+pub const HIGH_SPEED_PERIPHERAL_FREQ: usize = PROFILE.high_speed_peripheral_freq;
+pub const PERIPHERAL_FREQ: usize = PROFILE.peripheral_freq;
+pub const USB_FREQ: usize = PROFILE.usb_freq;
+pub const AON_FREQ: usize = PROFILE.aon_freq;
+
+pub const UART_BAUD_RATE: u32 = PROFILE.uart_baud_rate;
+
+/// Busy waits until `ready` returns true
+///
+/// Returns `Err(Error::Timeout)` if it doesn't within [`Profile::poll_timeout_cycles`] of the
+/// current profile.
+pub fn poll(mut ready: impl FnMut() -> bool) -> Result<(), Error> {
+    let start = cycles();
+    while !ready() {
+        if cycles().wrapping_sub(start) > PROFILE.poll_timeout_cycles() {
+            return Err(Error::Timeout);
+        }
+        core::hint::spin_loop();
+    }
+    Ok(())
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod host_tests {
+    use super::*;
+
+    #[test]
+    fn uart_baud_rates() {
+        // The NCO of the UART is 16 bits wide
+        for platform in Platform::ALL {
+            let profile = platform.profile();
+            let nco = ((profile.uart_baud_rate as u64) << 20) / profile.peripheral_freq as u64;
+            assert!(nco < 1 << 16, "{}", platform.name());
+        }
+    }
+
+    #[test]
+    fn current() {
+        assert_eq!(PROFILE, Platform::current().profile());
+        assert_eq!(UART_BAUD_RATE, PROFILE.uart_baud_rate);
+        assert_eq!(Platform::Cw310.profile().poll_timeout_cycles(), 2_400_000);
+    }
+
+    #[test]
+    fn poll_timeout() {
+        let mut polls = 0;
+        assert_eq!(
+            poll(|| {
+                polls += 1;
+                polls == 3
+            }),
+            Ok(())
+        );
+        assert_eq!(poll(|| false), Err(Error::Timeout));
+    }
+}
@@ -7,71 +7,82 @@
 
 use core::fmt::Write;
 
-use crate::synch::Lock;
-
-use super::{addresses, platform};
-use opentitan_macros::registers;
+use super::{
+    addresses,
+    peripherals::{self, ip},
+    platform, Peripheral,
+};
+use crate::Error;
 use tock_registers::interfaces::{Readable, Writeable};
 
-#[registers("hw/ip/uart/data/uart.hjson")]
+#[cfg(not(feature = "pregenerated"))]
+#[opentitan_macros::registers("hw/ip/uart/data/uart.hjson")]
 pub struct UartRegisters;
-
-const UART0: *mut UartRegisters = addresses::UART0 as *mut UartRegisters;
-static mut UART0_LOCK: Lock = Lock::new();
+#[cfg(feature = "pregenerated")]
+include!("../generated/devices/uart.rs");
 
 /// Returns a pointer to the registers of uart0
 ///
-/// This should only be used if either [`UartRaw`] or [`Uart`] do not meet the
-/// requirements (eg. performance or functionality)
-///
 /// # Safety
-/// Reading and modifying the uart registers may have potential side effects.
-/// Usage of the returned pointer is therefore inherently unsafe.
+/// Uart0 must not be used through its handle at the same time.
+#[deprecated(
+    note = "create a `Uart` from the handle of `Peripherals::take`, see `Uart::registers`"
+)]
 pub unsafe fn get_uart0_registers() -> *const UartRegisters {
-    UART0
+    // Unlike `Uart::new`, this must not reconfigure uart0
+    peripherals::Uart0::ADDRESS.ptr()
 }
 
-/// Returns a pointer to the uart0
-///
-/// This should only be used if [`Uart`] does not meet the
-/// requirements (eg. performance or functionality)
-///
-/// The returned value is an unsafe wrapper for the [`UartRegisters`] struct
-/// that implements a set of commonly used functionality.
+/// Returns a pointer to the registers of the uart `instance`, `None` if there is no such uart
 ///
 /// # Safety
-/// Reading and modifying the uart registers may have potential side effects.
-/// Usage of the returned pointer is therefore inherently unsafe.
-pub unsafe fn get_uart0_raw() -> *mut impl UartRaw {
-    UART0
+/// The uart must not be used through its handle at the same time.
+#[deprecated(
+    note = "create a `Uart` from the handle of `Peripherals::take`, see `Uart::registers`"
+)]
+pub unsafe fn get_uart_registers(instance: usize) -> Option<*mut UartRegisters> {
+    addresses::UART.get(instance).map(|address| address.ptr())
 }
 
-pub unsafe fn get_panic_uart() -> Uart {
-    unsafe { Uart::new(UART0, &mut UART0_LOCK) }
+/// Returns a pointer to uart0, an unsafe wrapper that implements commonly used functionality
+///
+/// # Safety
+/// Uart0 must not be used through its handle at the same time.
+#[deprecated(
+    note = "create a `Uart` from the handle of `Peripherals::take`, see `Uart::registers`"
+)]
+pub unsafe fn get_uart0_raw() -> *mut impl UartRaw {
+    peripherals::Uart0::ADDRESS.ptr::<UartRegisters>()
 }
 
-pub fn get_uart0() -> Result<Uart, ()> {
-    unsafe {
-        if UART0_LOCK.try_lock().is_ok() {
-            Ok(Uart::new(UART0, &mut UART0_LOCK))
-        } else {
-            Err(())
-        }
-    }
+/// Returns a driver of uart0 for output on panics, regardless of other users of uart0
+///
+/// Takes the uart of stdout, so its pending output isn't dropped by reconfiguring uart0.
+///
+/// # Safety
+/// Output of other drivers of uart0 may be interleaved with the output of the returned driver.
+pub unsafe fn get_panic_uart() -> Uart<peripherals::Uart0> {
+    crate::print::take_stdout().unwrap_or_else(|| Uart::new(peripherals::Uart0::steal()))
 }
 
 pub trait UartRaw {
     unsafe fn configure(&mut self, baudrate: Option<u32>);
 
-    unsafe fn send_blocking(&mut self, data: &[u8]);
+    /// Returns `Err(Error::Timeout)` if the transmit FIFO stays full
+    unsafe fn send_blocking(&mut self, data: &[u8]) -> Result<(), Error>;
 
-    unsafe fn recieve_blocking(&mut self, data: &mut [u8]);
+    /// Returns `Err(Error::Timeout)` if no data is received in time, the data received until then
+    /// is stored at the start of `data`
+    unsafe fn recieve_blocking(&mut self, data: &mut [u8]) -> Result<(), Error>;
 
-    unsafe fn try_send(&mut self, data: &u8) -> Result<(), ()>;
+    /// Returns `Err(Error::Busy)` if the transmit FIFO is full
+    unsafe fn try_send(&mut self, data: &u8) -> Result<(), Error>;
 
-    unsafe fn try_recieve(&mut self, data: &mut u8) -> Result<(), ()>;
+    /// Returns `Err(Error::Busy)` if the receive FIFO is empty
+    unsafe fn try_recieve(&mut self, data: &mut u8) -> Result<(), Error>;
 
-    unsafe fn flush(&mut self);
+    /// Returns `Err(Error::Timeout)` if the transmission does not finish in time
+    unsafe fn flush(&mut self) -> Result<(), Error>;
 }
 
 impl UartRaw for UartRegisters {
@@ -85,94 +96,168 @@ impl UartRaw for UartRegisters {
             .write(fifo_ctrl::txrst::SET + fifo_ctrl::rxrst::SET);
     }
 
-    unsafe fn send_blocking(&mut self, data: &[u8]) {
+    unsafe fn send_blocking(&mut self, data: &[u8]) -> Result<(), Error> {
         for val in data {
-            while self.try_send(val).is_err() {}
+            platform::poll(|| self.try_send(val).is_ok())?;
         }
+        Ok(())
     }
 
-    unsafe fn recieve_blocking(&mut self, data: &mut [u8]) {
+    unsafe fn recieve_blocking(&mut self, data: &mut [u8]) -> Result<(), Error> {
         for val in data {
-            while self.try_recieve(val).is_err() {}
+            platform::poll(|| self.try_recieve(val).is_ok())?;
         }
+        Ok(())
     }
 
-    unsafe fn try_send(&mut self, data: &u8) -> Result<(), ()> {
+    unsafe fn try_send(&mut self, data: &u8) -> Result<(), Error> {
         if !self.status.is_set(status::txfull) {
             self.wdata.write(wdata::data.val(*data as u32));
             Ok(())
         } else {
-            Err(())
+            Err(Error::Busy)
         }
     }
 
-    unsafe fn try_recieve(&mut self, data: &mut u8) -> Result<(), ()> {
+    unsafe fn try_recieve(&mut self, data: &mut u8) -> Result<(), Error> {
         if !self.status.is_set(status::rxempty) {
             *data = self.rdata.read(rdata::data) as u8;
             Ok(())
         } else {
-            Err(())
+            Err(Error::Busy)
         }
     }
 
-    unsafe fn flush(&mut self) {
-        while !self.status.is_set(status::txidle) {}
+    unsafe fn flush(&mut self) -> Result<(), Error> {
+        platform::poll(|| self.status.is_set(status::txidle))
     }
 }
 
-pub struct Uart {
-    regs: *mut UartRegisters,
-    lock: *mut Lock,
+/// Driver of a uart, owns the handle of the instance it was created from
+pub struct Uart<P: Peripheral<Ip = ip::Uart>> {
+    uart: P,
 }
 
-impl Uart {
-    unsafe fn new(regs: *mut UartRegisters, lock: *mut Lock) -> Uart {
-        let mut uart = Uart { regs, lock };
-        uart.reconfigure(None);
+impl<P: Peripheral<Ip = ip::Uart>> Uart<P> {
+    /// Creates the driver of the uart of the handle `uart` & configures it with the default baud
+    /// rate of the platform
+    pub fn new(uart: P) -> Self {
+        let mut uart = Uart { uart };
+        // A timeout only drops the output of the previous user when the FIFOs are reset
+        let _ = uart.reconfigure(None);
         uart
     }
 
-    pub fn reconfigure(&mut self, baudrate: Option<u32>) {
-        unsafe {
-            (*self.regs).flush();
-            (*self.regs).configure(baudrate);
-        }
+    /// Returns the handle of the instance
+    pub fn free(self) -> P {
+        self.uart
     }
 
-    pub fn send_blocking(&mut self, data: &[u8]) {
-        unsafe {
-            (*self.regs).send_blocking(data);
-        }
+    /// Returns a pointer to the registers of the instance, for functionality the driver lacks
+    ///
+    /// The registers also implement [`UartRaw`]. Accesses through the pointer may interfere with
+    /// the operations of the driver.
+    pub fn registers(&self) -> *mut UartRegisters {
+        P::ADDRESS.ptr()
     }
 
-    pub fn recieve_blocking(&mut self, data: &mut [u8]) {
+    /// Waits for pending output and sets the baud rate, the default one of the platform for `None`
+    ///
+    /// Returns `Err(Error::Timeout)` if the pending output is not sent in time, the uart is
+    /// configured anyway.
+    pub fn reconfigure(&mut self, baudrate: Option<u32>) -> Result<(), Error> {
         unsafe {
-            (*self.regs).recieve_blocking(data);
+            let flushed = (*self.registers()).flush();
+            (*self.registers()).configure(baudrate);
+            flushed
         }
     }
-}
 
-impl Drop for Uart {
-    fn drop(&mut self) {
-        unsafe { (*self.lock).unlock() }
+    /// Returns `Err(Error::Timeout)` if the transmit FIFO stays full
+    pub fn send_blocking(&mut self, data: &[u8]) -> Result<(), Error> {
+        unsafe { (*self.registers()).send_blocking(data) }
+    }
+
+    /// Returns `Err(Error::Timeout)` if no data is received in time
+    pub fn recieve_blocking(&mut self, data: &mut [u8]) -> Result<(), Error> {
+        unsafe { (*self.registers()).recieve_blocking(data) }
     }
 }
 
-impl Write for Uart {
+impl<P: Peripheral<Ip = ip::Uart>> Write for Uart<P> {
     fn write_str(&mut self, data: &str) -> core::fmt::Result {
         unsafe {
             for c in data.as_bytes() {
                 // Convert \n to \r\n
                 if *c == '\n' as u8 {
-                    while (*self.regs).try_send(&('\r' as u8)).is_err() {
-                        core::hint::spin_loop();
-                    }
-                }
-                while (*self.regs).try_send(c).is_err() {
-                    core::hint::spin_loop();
+                    (*self.registers())
+                        .send_blocking(b"\r")
+                        .map_err(|_| core::fmt::Error)?;
                 }
+                (*self.registers())
+                    .send_blocking(core::slice::from_ref(c))
+                    .map_err(|_| core::fmt::Error)?;
             }
             Ok(())
         }
     }
 }
+
+#[cfg(all(test, not(target_os = "none")))]
+mod host_tests {
+    use super::*;
+    use crate::mock::MockRegisters;
+
+    #[test]
+    fn configure() {
+        let uart = MockRegisters::<UartRegisters>::new();
+        unsafe { (*uart.ptr()).configure(Some(115_200)) };
+
+        let nco = (115_200u64 << 20) / platform::PERIPHERAL_FREQ as u64;
+        let ctrl = ctrl::nco.val((nco & 0xffff) as u32) + ctrl::rx::SET + ctrl::tx::SET;
+        assert_eq!(uart.writes(&uart.ctrl), [ctrl.value]);
+        let reset = fifo_ctrl::txrst::SET + fifo_ctrl::rxrst::SET;
+        assert_eq!(uart.writes(&uart.fifo_ctrl), [reset.value]);
+    }
+
+    #[test]
+    fn tx_fifo_full() {
+        let uart = MockRegisters::<UartRegisters>::new();
+        let fifo = uart.fifo(&uart.wdata, &uart.status, status::txfull, 2);
+
+        unsafe {
+            assert_eq!((*uart.ptr()).try_send(&b'a'), Ok(()));
+            assert_eq!((*uart.ptr()).try_send(&b'b'), Ok(()));
+            assert_eq!((*uart.ptr()).try_send(&b'c'), Err(Error::Busy));
+            assert_eq!(fifo.pop(), Some(b'a' as u32));
+            assert_eq!((*uart.ptr()).try_send(&b'c'), Ok(()));
+        }
+        assert_eq!(uart.writes(&uart.wdata), b"abc".map(u32::from));
+        assert_eq!(fifo.len(), 2);
+    }
+
+    #[test]
+    fn recieve_blocking() {
+        let uart = MockRegisters::<UartRegisters>::new();
+        uart.clear_after(&uart.status, status::rxempty, 3);
+        uart.push_reads(&uart.rdata, [0x4f, 0x54]);
+
+        let mut data = [0; 2];
+        assert_eq!(unsafe { (*uart.ptr()).recieve_blocking(&mut data) }, Ok(()));
+        assert_eq!(data, [0x4f, 0x54]);
+        assert_eq!(uart.reads(&uart.status), 5);
+        assert_eq!(uart.reads(&uart.rdata), 2);
+    }
+
+    #[test]
+    fn flush() {
+        let uart = MockRegisters::<UartRegisters>::new();
+        uart.set_after(&uart.status, status::txidle, 3);
+
+        assert_eq!(unsafe { (*uart.ptr()).flush() }, Ok(()));
+        assert_eq!(uart.reads(&uart.status), 4);
+
+        uart.clear_after(&uart.status, status::txidle, 0);
+        assert_eq!(unsafe { (*uart.ptr()).flush() }, Err(Error::Timeout));
+    }
+}
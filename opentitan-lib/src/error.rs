@@ -0,0 +1,84 @@
+//! Error type of the drivers & synchronization primitives
+//!
+//! Modules with more specific errors, like [`WriteError`] & [`WindowError`] of `registers`,
+//! convert into [`Error`], so callers can use `?` across them.
+
+use core::fmt;
+
+use crate::registers::{WindowError, WriteError};
+
+/// Error of the drivers & synchronization primitives of the lib
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The resource is in use, eg. a lock is held or a FIFO is full, retrying later may succeed
+    Busy,
+    /// The hardware did not become ready within the poll timeout of the platform, see
+    /// `platform::poll`
+    Timeout,
+    /// A register is locked by its regwen until the next reset
+    Locked,
+    /// The IP reported an error in its status registers
+    Status(&'static str),
+    /// The IP raised a recoverable alert, eg. for an update error of a shadowed register
+    RecoverableAlert,
+    /// The IP raised a fatal alert, it stays in an error state until reset
+    FatalAlert,
+    /// An argument is outside of the supported range, eg. an unknown enum value or an access
+    /// outside of a window
+    InvalidArgument,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Busy => write!(f, "resource is busy"),
+            Error::Timeout => write!(f, "timeout waiting for the hardware"),
+            Error::Locked => write!(f, "register is locked by its regwen"),
+            Error::Status(status) => write!(f, "hardware error: {}", status),
+            Error::RecoverableAlert => write!(f, "recoverable alert"),
+            Error::FatalAlert => write!(f, "fatal alert"),
+            Error::InvalidArgument => write!(f, "invalid argument"),
+        }
+    }
+}
+
+impl From<WriteError> for Error {
+    fn from(error: WriteError) -> Self {
+        match error {
+            WriteError::Locked => Error::Locked,
+            // Shadowed registers raise a recoverable alert for update errors
+            WriteError::UpdateError => Error::RecoverableAlert,
+            // Only returned for acknowledging a status interrupt, which is a misuse of the interrupt
+            WriteError::StatusInterrupt => Error::InvalidArgument,
+        }
+    }
+}
+
+impl From<WindowError> for Error {
+    fn from(_: WindowError) -> Self {
+        Error::InvalidArgument
+    }
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod host_tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        assert_eq!(Error::from(WriteError::Locked), Error::Locked);
+        assert_eq!(
+            Error::from(WriteError::UpdateError),
+            Error::RecoverableAlert
+        );
+        assert_eq!(
+            Error::from(WriteError::StatusInterrupt),
+            Error::InvalidArgument
+        );
+        assert_eq!(Error::from(WindowError::Unaligned), Error::InvalidArgument);
+        assert_eq!(
+            Error::Status("csrng command failed").to_string(),
+            "hardware error: csrng command failed"
+        );
+    }
+}
@@ -1,31 +1,53 @@
-#![no_std]
-#![no_main]
+// Tests on the host use the standard test harness & the mock register backend of `mock`
+#![cfg_attr(any(not(test), target_os = "none"), no_std)]
+#![cfg_attr(target_os = "none", no_main)]
 #![feature(naked_functions)]
-#![feature(custom_test_frameworks)]
-#![test_runner(crate::tests::test_runner)]
-#![reexport_test_harness_main = "test_main"]
+#![cfg_attr(target_os = "none", feature(custom_test_frameworks))]
+#![cfg_attr(target_os = "none", test_runner(crate::tests::test_runner))]
+#![cfg_attr(target_os = "none", reexport_test_harness_main = "test_main")]
+
+// Allows code generated by opentitan-macros to refer to this crate by name
+extern crate self as opentitan_lib;
 
 pub mod devices;
+pub mod error;
+#[cfg(target_os = "none")]
 pub mod interrupt;
 pub mod print;
+pub mod registers;
 pub mod synch;
 
+#[cfg(target_os = "none")]
 pub mod tests;
+#[cfg(feature = "trace_mmio")]
+pub mod trace;
+
+#[cfg(all(test, not(target_os = "none")))]
+pub mod mock;
 
-#[cfg(feature = "alloc")]
+#[cfg(all(feature = "alloc", target_os = "none"))]
 mod alloc;
 mod atomic;
 
+#[cfg(target_os = "none")]
 use core::{arch::asm, ptr};
-pub use opentitan_macros::entry;
+pub use error::Error;
+pub use opentitan_macros::{entry, registers};
+// Code generated by `#[registers]` refers to tock-registers through the lib, so applications
+// don't need to depend on it
+#[doc(hidden)]
+pub use tock_registers;
+#[cfg(target_os = "none")]
 use riscv::register::mtvec;
 
 /// Specifies the stack size
+#[cfg(target_os = "none")]
 #[no_mangle]
 #[link_section = ".stack_buffer"]
 pub static mut STACK_MEMORY: [u8; 0x4000] = [0; 0x4000];
 
 // Values provided by linker script
+#[cfg(target_os = "none")]
 extern "C" {
     static __global_pointer: usize;
 
@@ -56,6 +78,7 @@ extern "C" {
     pub fn main();
 }
 
+#[cfg(target_os = "none")]
 #[link_section = ".start"]
 #[export_name = "_start"]
 #[naked]
@@ -113,9 +136,13 @@ pub extern "C" fn _start() {
     }
 }
 
+#[cfg(target_os = "none")]
 #[export_name = "_init"]
 pub unsafe extern "C" fn _init() -> ! {
-    print::redirect_stdout(devices::uart::get_uart0().expect("Could not acquire uart for stdout"));
+    // Nothing is taken yet, stdout owns uart0 until it is taken back with `print::take_stdout`
+    print::redirect_stdout(devices::uart::Uart::new(
+        devices::peripherals::Uart0::steal(),
+    ));
     #[cfg(feature = "alloc")]
     {
         let heap_size = (ptr::addr_of!(_eheap) as usize) - (ptr::addr_of!(_sheap) as usize);
@@ -145,6 +172,7 @@ pub unsafe extern "C" fn _init() -> ! {
     }
 }
 
+#[cfg(target_os = "none")]
 #[inline]
 pub fn suspend() -> ! {
     loop {
@@ -154,6 +182,7 @@ pub fn suspend() -> ! {
     }
 }
 
+#[cfg(target_os = "none")]
 mod panic {
     use crate::{devices, suspend, tests};
     use core::panic::PanicInfo;
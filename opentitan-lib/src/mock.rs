@@ -0,0 +1,319 @@
+//! In-memory register file that backs the registers in tests on the host
+//!
+//! [`MockRegisters`] allocates a zeroed register block, eg. `MockRegisters<UartRegisters>`, and
+//! the driver code is called directly on it. Every access of one of its registers goes through
+//! this module, so tests can script the behavior of the hardware: bits that become set after a
+//! number of polls, values returned by consecutive reads of a register or FIFOs that fill up.
+//! The tests are run with the target of the host, eg.
+//! `cargo test -p opentitan-lib --target x86_64-unknown-linux-gnu`.
+//!
+//! ```ignore
+//! let uart = MockRegisters::<UartRegisters>::new();
+//! uart.set_after(&uart.status, status::txidle, 3);
+//! unsafe { (*uart.ptr()).flush() }.unwrap();
+//! assert_eq!(uart.reads(&uart.status), 4);
+//! ```
+//!
+//! Only 32 bit registers can be scripted. The register blocks of the IP instances, eg. the one of
+//! `Uart::new(peripherals::Uart0::steal())`, are not mapped on the host and can't be used in these
+//! tests.
+
+use std::{
+    alloc::{self, Layout},
+    cell::{Cell, RefCell},
+    collections::{HashMap, VecDeque},
+    mem,
+    ops::{Deref, Range},
+    ptr::{self, NonNull},
+    rc::Rc,
+};
+
+use tock_registers::{fields::Field, interfaces::Readable, RegisterLongName};
+
+/// Function applied to the value of a read or write, returns the value seen by the other side
+type Hook = Box<dyn FnMut(u32) -> u32>;
+
+#[derive(Default)]
+struct Register {
+    read_hooks: Vec<Hook>,
+    write_hooks: Vec<Hook>,
+    reads: usize,
+    writes: Vec<u32>,
+}
+
+/// Scripted registers of all live [`MockRegisters`] of a thread
+#[derive(Default)]
+struct RegisterFile {
+    blocks: Vec<Range<usize>>,
+    registers: HashMap<usize, Register>,
+}
+
+impl RegisterFile {
+    fn register(&mut self, addr: usize) -> Option<&mut Register> {
+        if self.blocks.iter().any(|block| block.contains(&addr)) {
+            Some(self.registers.entry(addr).or_default())
+        } else {
+            None
+        }
+    }
+}
+
+thread_local! {
+    // Tests run in parallel threads, each with its own register blocks
+    static REGISTER_FILE: RefCell<RegisterFile> = RefCell::new(RegisterFile::default());
+}
+
+/// Runs `f` on the register at `addr`, `None` if it isn't part of a mocked register block
+///
+/// Hooks are called while the register file is borrowed, they must not access registers.
+fn with_register<T>(addr: usize, f: impl FnOnce(&mut Register) -> T) -> Option<T> {
+    REGISTER_FILE.with(|file| file.borrow_mut().register(addr).map(f))
+}
+
+/// Replacement of [`core::ptr::read_volatile`] for the register types
+pub(crate) unsafe fn read_volatile<T: Copy>(src: *const T) -> T {
+    let value = ptr::read_volatile(src);
+    if mem::size_of::<T>() != 4 {
+        return value;
+    }
+    let raw = mem::transmute_copy::<T, u32>(&value);
+    let raw = with_register(src as usize, |register| {
+        register.reads += 1;
+        register
+            .read_hooks
+            .iter_mut()
+            .fold(raw, |value, hook| hook(value))
+    });
+    raw.map_or(value, |raw| mem::transmute_copy::<u32, T>(&raw))
+}
+
+/// Replacement of [`core::ptr::write_volatile`] for the register types
+pub(crate) unsafe fn write_volatile<T: Copy>(dst: *mut T, value: T) {
+    let mut value = value;
+    if mem::size_of::<T>() == 4 {
+        let raw = mem::transmute_copy::<T, u32>(&value);
+        let raw = with_register(dst as usize, |register| {
+            register.writes.push(raw);
+            register
+                .write_hooks
+                .iter_mut()
+                .fold(raw, |value, hook| hook(value))
+        });
+        if let Some(raw) = raw {
+            value = mem::transmute_copy::<u32, T>(&raw);
+        }
+    }
+    ptr::write_volatile(dst, value)
+}
+
+thread_local! {
+    static CYCLES: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Replacement of the cycle counter used by `platform::poll`, every read counts as one cycle
+pub(crate) fn cycles() -> u64 {
+    CYCLES.with(|cycles| {
+        let value = cycles.get();
+        cycles.set(value + 1);
+        value
+    })
+}
+
+/// Register block of type `T` backed by host memory, all registers are zero initially
+///
+/// Hooks registered for the same register are applied in the order they were added, each gets
+/// the value returned by the previous one.
+pub struct MockRegisters<T> {
+    block: NonNull<T>,
+}
+
+impl<T> MockRegisters<T> {
+    pub fn new() -> Self {
+        let layout = Layout::new::<T>();
+        let block = unsafe { alloc::alloc_zeroed(layout) } as *mut T;
+        let block = NonNull::new(block).unwrap_or_else(|| alloc::handle_alloc_error(layout));
+        let start = block.as_ptr() as usize;
+        REGISTER_FILE.with(|file| file.borrow_mut().blocks.push(start..start + layout.size()));
+        MockRegisters { block }
+    }
+
+    /// Returns the pointer to the registers, like the `registers` method of the drivers
+    pub fn ptr(&self) -> *mut T {
+        self.block.as_ptr()
+    }
+
+    /// Sets the value of `register` without calling any hooks, eg. to its reset value
+    pub fn set<R>(&self, register: &R, value: u32) {
+        let addr = self.addr(register);
+        unsafe { ptr::write_volatile(addr as *mut u32, value) }
+    }
+
+    /// Calls `hook` with the stored value on every read of `register`, the driver reads the
+    /// returned value
+    pub fn on_read<R>(&self, register: &R, hook: impl FnMut(u32) -> u32 + 'static) {
+        self.with(register, |register| {
+            register.read_hooks.push(Box::new(hook))
+        });
+    }
+
+    /// Calls `hook` with the written value on every write of `register`, the returned value is
+    /// stored
+    pub fn on_write<R>(&self, register: &R, hook: impl FnMut(u32) -> u32 + 'static) {
+        self.with(register, |register| {
+            register.write_hooks.push(Box::new(hook))
+        });
+    }
+
+    /// The bits of `field` read as cleared for the first `polls` reads of `register` & as set
+    /// afterwards
+    pub fn set_after<L, R>(&self, register: &R, field: Field<u32, L>, polls: usize)
+    where
+        L: RegisterLongName,
+        R: Readable<T = u32, R = L>,
+    {
+        let bits = field.mask << field.shift;
+        let mut reads = 0;
+        self.on_read(register, move |value| {
+            reads += 1;
+            if reads > polls {
+                value | bits
+            } else {
+                value & !bits
+            }
+        });
+    }
+
+    /// The bits of `field` read as set for the first `polls` reads of `register` & as cleared
+    /// afterwards
+    pub fn clear_after<L, R>(&self, register: &R, field: Field<u32, L>, polls: usize)
+    where
+        L: RegisterLongName,
+        R: Readable<T = u32, R = L>,
+    {
+        let bits = field.mask << field.shift;
+        let mut reads = 0;
+        self.on_read(register, move |value| {
+            reads += 1;
+            if reads > polls {
+                value & !bits
+            } else {
+                value | bits
+            }
+        });
+    }
+
+    /// Consecutive reads of `register` return `values`, then the stored value
+    pub fn push_reads<R>(&self, register: &R, values: impl IntoIterator<Item = u32>) {
+        let mut values = values.into_iter().collect::<VecDeque<_>>();
+        self.on_read(register, move |value| values.pop_front().unwrap_or(value));
+    }
+
+    /// Values written to the data register `data` are queued in a FIFO with `depth` entries
+    ///
+    /// The bits of `full` are set in the reads of `status` while the FIFO is full, writes to a
+    /// full FIFO are dropped like by the hardware. The returned handle drains the FIFO.
+    pub fn fifo<D, L, S>(&self, data: &D, status: &S, full: Field<u32, L>, depth: usize) -> Fifo
+    where
+        L: RegisterLongName,
+        S: Readable<T = u32, R = L>,
+    {
+        let fifo = Fifo::default();
+        let entries = fifo.entries.clone();
+        self.on_write(data, move |value| {
+            let mut entries = entries.borrow_mut();
+            if entries.len() < depth {
+                entries.push_back(value);
+            }
+            value
+        });
+        let entries = fifo.entries.clone();
+        let bits = full.mask << full.shift;
+        self.on_read(status, move |value| {
+            if entries.borrow().len() >= depth {
+                value | bits
+            } else {
+                value & !bits
+            }
+        });
+        fifo
+    }
+
+    /// Returns how often `register` was read
+    pub fn reads<R>(&self, register: &R) -> usize {
+        self.with(register, |register| register.reads)
+    }
+
+    /// Returns all values written to `register`, before they were passed to the write hooks
+    pub fn writes<R>(&self, register: &R) -> Vec<u32> {
+        self.with(register, |register| register.writes.clone())
+    }
+
+    /// Returns the address of `register`, which has to be a 32 bit register of this block
+    fn addr<R>(&self, register: &R) -> usize {
+        let addr = register as *const R as usize;
+        let start = self.block.as_ptr() as usize;
+        assert!(
+            (start..start + mem::size_of::<T>()).contains(&addr),
+            "register is not part of the mocked block"
+        );
+        assert_eq!(
+            mem::size_of::<R>(),
+            4,
+            "only 32 bit registers can be mocked"
+        );
+        addr
+    }
+
+    fn with<R, U>(&self, register: &R, f: impl FnOnce(&mut Register) -> U) -> U {
+        with_register(self.addr(register), f).unwrap()
+    }
+}
+
+impl<T> Default for MockRegisters<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Deref for MockRegisters<T> {
+    type Target = T;
+
+    /// Allows to name the registers, eg. `&uart.status`
+    fn deref(&self) -> &T {
+        unsafe { self.block.as_ref() }
+    }
+}
+
+impl<T> Drop for MockRegisters<T> {
+    fn drop(&mut self) {
+        let start = self.block.as_ptr() as usize;
+        let block = start..start + mem::size_of::<T>();
+        REGISTER_FILE.with(|file| {
+            let mut file = file.borrow_mut();
+            file.blocks.retain(|other| *other != block);
+            file.registers.retain(|addr, _| !block.contains(addr));
+        });
+        unsafe { alloc::dealloc(self.block.as_ptr() as *mut u8, Layout::new::<T>()) }
+    }
+}
+
+/// Handle of a FIFO created by [`MockRegisters::fifo`]
+#[derive(Default)]
+pub struct Fifo {
+    entries: Rc<RefCell<VecDeque<u32>>>,
+}
+
+impl Fifo {
+    /// Removes the oldest entry, like the hardware when it sends or processes it
+    pub fn pop(&self) -> Option<u32> {
+        self.entries.borrow_mut().pop_front()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.borrow().is_empty()
+    }
+}
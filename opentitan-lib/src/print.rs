@@ -1,12 +1,35 @@
 //! Adds support for the print macro
-use crate::devices::uart::Uart;
+use core::ptr;
 
-pub static mut STD_OUT: Option<Uart> = None;
+use crate::devices::{peripherals::Uart0, uart::Uart};
 
-pub unsafe fn redirect_stdout(uart: Uart) {
+/// Uart of stdout, set up with uart0 by the lib before `main`
+pub static mut STD_OUT: Option<Uart<Uart0>> = None;
+
+/// Prints to `uart` from now on
+///
+/// Stdout owns uart0 while it prints, [`Peripherals::take`](crate::devices::Peripherals::take)
+/// hands out its handle as `None` then.
+///
+/// # Safety
+/// Must not be called while printing, eg. from an interrupt handler.
+pub unsafe fn redirect_stdout(uart: Uart<Uart0>) {
     STD_OUT = Some(uart)
 }
 
+/// Stops printing & returns the uart of stdout, `None` if it was taken before
+///
+/// # Safety
+/// Must not be called while printing, eg. from an interrupt handler.
+pub unsafe fn take_stdout() -> Option<Uart<Uart0>> {
+    ptr::replace(ptr::addr_of_mut!(STD_OUT), None)
+}
+
+/// Returns whether stdout owns uart0
+pub(crate) fn owns_uart0() -> bool {
+    unsafe { (*ptr::addr_of!(STD_OUT)).is_some() }
+}
+
 /// Print macro that can be used like the print macro from rust's standard library.
 #[macro_export]
 macro_rules! print {
@@ -15,7 +38,9 @@ macro_rules! print {
         unsafe {
             use core::fmt::Write;
             if let Some(out) = &mut $crate::print::STD_OUT {
-                write!(out, $($arg)*).unwrap();
+                // Output is dropped if the uart is stuck, printing a panic would
+                // only get stuck on the same uart
+                let _ = write!(out, $($arg)*);
             }
         }
     );
@@ -29,7 +54,7 @@ macro_rules! println {
         unsafe {
             use core::fmt::Write;
             if let Some(out) = &mut $crate::print::STD_OUT {
-                writeln!(out).unwrap();
+                let _ = writeln!(out);
             }
         }
     );
@@ -38,7 +63,7 @@ macro_rules! println {
         unsafe {
             use core::fmt::Write;
             if let Some(out) = &mut $crate::print::STD_OUT {
-                writeln!(out, $($arg)*).unwrap();
+                let _ = writeln!(out, $($arg)*);
             }
         }
     );
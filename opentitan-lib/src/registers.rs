@@ -0,0 +1,882 @@
+//! Register types used by the code generated with the `#[registers]` macro
+//!
+//! tock-registers only knows plain read/write semantics, opentitan IPs however also use registers
+//! where writing a value clears or sets bits (eg. interrupt state registers). Using a plain
+//! `ReadWrite` register for those makes read-modify-write operations possible, which silently
+//! clear other pending bits. The types in this module only offer the operations that make sense
+//! for the respective access policy.
+//!
+//! All register types, including the plain [`ReadOnly`], [`WriteOnly`] & [`ReadWrite`] ones, and
+//! windows access the hardware through the same two functions, so the `trace_mmio` feature can log
+//! every access.
+//! In tests on the host these accesses go to the register file of [`crate::mock`] instead.
+
+use core::{cell::UnsafeCell, fmt, marker::PhantomData};
+
+#[cfg(all(test, not(target_os = "none")))]
+use crate::mock::{read_volatile, write_volatile};
+#[cfg(not(all(test, not(target_os = "none"))))]
+use core::ptr::{read_volatile, write_volatile};
+
+use tock_registers::{
+    fields::{Field, FieldValue},
+    interfaces::{Readable, Writeable},
+    RegisterLongName, UIntLike,
+};
+
+/// Reads the register `R` at `src`, all hardware accesses go through this function & [`write`]
+///
+/// Accesses that don't belong to a register with fields, like windows, use `()` for `R` and are
+/// traced with their offset & value only.
+#[inline]
+#[cfg_attr(
+    not(feature = "trace_mmio"),
+    allow(clippy::extra_unused_type_parameters)
+)]
+unsafe fn read<T: UIntLike, R: RegisterInfo>(src: *const T) -> T {
+    let value = read_volatile(src);
+    #[cfg(feature = "trace_mmio")]
+    crate::trace::access::<T, R>(src as usize, value, false);
+    value
+}
+
+/// Writes `value` to the register `R` at `dst`, see [`read`]
+#[inline]
+#[cfg_attr(
+    not(feature = "trace_mmio"),
+    allow(clippy::extra_unused_type_parameters)
+)]
+unsafe fn write<T: UIntLike, R: RegisterInfo>(dst: *mut T, value: T) {
+    #[cfg(feature = "trace_mmio")]
+    crate::trace::access::<T, R>(dst as usize, value, true);
+    write_volatile(dst, value)
+}
+
+macro_rules! register_type {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        #[repr(transparent)]
+        pub struct $name<T: UIntLike, R: RegisterInfo = ()> {
+            value: UnsafeCell<T>,
+            associated_register: PhantomData<R>,
+        }
+
+        impl<T: UIntLike, R: RegisterInfo> $name<T, R> {
+            #[inline]
+            #[allow(dead_code)]
+            fn read_raw(&self) -> T {
+                unsafe { read::<T, R>(self.value.get()) }
+            }
+
+            #[inline]
+            #[allow(dead_code)]
+            fn write_raw(&self, value: T) {
+                unsafe { write::<T, R>(self.value.get(), value) }
+            }
+        }
+    };
+}
+
+macro_rules! impl_readable {
+    ($name:ident) => {
+        impl<T: UIntLike, R: RegisterInfo> Readable for $name<T, R> {
+            type T = T;
+            type R = R;
+
+            #[inline]
+            fn get(&self) -> T {
+                self.read_raw()
+            }
+        }
+    };
+}
+
+macro_rules! impl_writeable {
+    ($name:ident) => {
+        impl<T: UIntLike, R: RegisterInfo> Writeable for $name<T, R> {
+            type T = T;
+            type R = R;
+
+            #[inline]
+            fn set(&self, value: T) {
+                self.write_raw(value)
+            }
+        }
+    };
+}
+
+/// Register long name with the information needed to trace accesses
+///
+/// Implemented by the `#[registers]` macro for the long name of every register with fields.
+pub trait RegisterInfo: RegisterLongName {
+    /// Name of the register, or of the registers that share the long name
+    const NAME: &'static str = "";
+    /// Names, offsets & widths of the fields
+    const FIELDS: &'static [(&'static str, usize, usize)] = &[];
+}
+
+impl RegisterInfo for () {}
+
+register_type!(
+    /// Read only register (`ro`)
+    ReadOnly
+);
+impl_readable!(ReadOnly);
+
+register_type!(
+    /// Write only register (`wo`)
+    WriteOnly
+);
+impl_writeable!(WriteOnly);
+
+register_type!(
+    /// Read/write register (`rw`)
+    ReadWrite
+);
+impl_readable!(ReadWrite);
+impl_writeable!(ReadWrite);
+
+register_type!(
+    /// Read only register whose bits are cleared by reading it (`rc`)
+    ///
+    /// Every read, including [`Readable::is_set`] & co., clears the register.
+    ReadClear
+);
+impl_readable!(ReadClear);
+
+register_type!(
+    /// Register whose bits are cleared by writing a 1 to them (`rw1c`)
+    ReadWriteOneToClear
+);
+impl_readable!(ReadWriteOneToClear);
+
+impl<T: UIntLike, R: RegisterInfo> ReadWriteOneToClear<T, R> {
+    /// Clears all bits of the field, leaving the other fields unchanged
+    #[inline]
+    pub fn clear(&self, field: Field<T, R>) {
+        self.write_raw(field.mask << field.shift);
+    }
+
+    /// Clears all bits of the register
+    #[inline]
+    pub fn clear_all(&self) {
+        self.write_raw(!T::zero());
+    }
+}
+
+register_type!(
+    /// Register whose bits are cleared by writing a 0 to them (`rw0c`)
+    ReadWriteZeroToClear
+);
+impl_readable!(ReadWriteZeroToClear);
+
+impl<T: UIntLike, R: RegisterInfo> ReadWriteZeroToClear<T, R> {
+    /// Clears all bits of the field, leaving the other fields unchanged
+    #[inline]
+    pub fn clear(&self, field: Field<T, R>) {
+        self.write_raw(!(field.mask << field.shift));
+    }
+
+    /// Clears all bits of the register
+    #[inline]
+    pub fn clear_all(&self) {
+        self.write_raw(T::zero());
+    }
+}
+
+register_type!(
+    /// Register that always reads as 0 and whose bits are cleared by writing a 1 to them (`r0w1c`)
+    ///
+    /// Since reads carry no information, writing arbitrary values through [`Writeable`] is safe.
+    ReadZeroWriteOneToClear
+);
+
+impl_writeable!(ReadZeroWriteOneToClear);
+
+impl<T: UIntLike, R: RegisterInfo> ReadZeroWriteOneToClear<T, R> {
+    /// Clears all bits of the field, leaving the other fields unchanged
+    #[inline]
+    pub fn clear(&self, field: Field<T, R>) {
+        self.write_raw(field.mask << field.shift);
+    }
+}
+
+register_type!(
+    /// Write only register whose bits are cleared by writing a 1 to them (`w1c`)
+    WriteOneToClear
+);
+
+impl<T: UIntLike, R: RegisterInfo> WriteOneToClear<T, R> {
+    /// Clears all bits of the field, leaving the other fields unchanged
+    #[inline]
+    pub fn clear(&self, field: Field<T, R>) {
+        self.write_raw(field.mask << field.shift);
+    }
+
+    /// Clears all bits of the register
+    #[inline]
+    pub fn clear_all(&self) {
+        self.write_raw(!T::zero());
+    }
+}
+
+register_type!(
+    /// Register whose bits are set by writing a 1 to them (`rw1s`)
+    ReadWriteOneToSet
+);
+impl_readable!(ReadWriteOneToSet);
+
+impl<T: UIntLike, R: RegisterInfo> ReadWriteOneToSet<T, R> {
+    /// Sets all bits of the field, leaving the other fields unchanged
+    #[inline]
+    pub fn set(&self, field: Field<T, R>) {
+        self.write_raw(field.mask << field.shift);
+    }
+}
+
+register_type!(
+    /// Write only register whose bits are set by writing a 1 to them (`w1s`)
+    WriteOneToSet
+);
+
+impl<T: UIntLike, R: RegisterInfo> WriteOneToSet<T, R> {
+    /// Sets all bits of the field, leaving the other fields unchanged
+    #[inline]
+    pub fn set(&self, field: Field<T, R>) {
+        self.write_raw(field.mask << field.shift);
+    }
+}
+
+/// Register long name of a shadowed register
+///
+/// Implemented by the `#[registers]` macro for every shadowed register that has fields.
+pub trait ShadowedRegister: RegisterInfo {
+    /// Offset of the register reporting update errors relative to the shadowed register & the
+    /// mask of the update error bit, `None` if the IP does not expose one
+    const UPDATE_ERROR: Option<(isize, u32)>;
+}
+
+impl ShadowedRegister for () {
+    const UPDATE_ERROR: Option<(isize, u32)> = None;
+}
+
+register_type!(
+    /// Shadowed read/write register (`shadowed: "true"`)
+    ///
+    /// Writes only take effect once the same value was written twice. All write operations
+    /// therefore perform the double write and then check whether the IP reported an update error,
+    /// which happens if the two written values differed. Reads are not affected.
+    ShadowedReadWrite
+);
+impl_readable!(ShadowedReadWrite);
+
+impl<T: UIntLike, R: ShadowedRegister> ShadowedReadWrite<T, R> {
+    /// Writes the raw value to the register
+    ///
+    /// Returns `Err` if the IP reported an update error.
+    #[inline]
+    pub fn set(&self, value: T) -> Result<(), WriteError> {
+        self.write_raw(value);
+        self.write_raw(value);
+        self.check_update_error()
+    }
+
+    /// Writes the fields to the register, all other fields are set to 0
+    ///
+    /// Returns `Err` if the IP reported an update error.
+    #[inline]
+    pub fn write(&self, field: FieldValue<T, R>) -> Result<(), WriteError> {
+        self.set(field.value)
+    }
+
+    /// Writes the fields to the register, all other fields keep their current value
+    ///
+    /// Returns `Err` if the IP reported an update error.
+    #[inline]
+    pub fn modify(&self, field: FieldValue<T, R>) -> Result<(), WriteError> {
+        self.set(field.modify(self.get()))
+    }
+
+    fn check_update_error(&self) -> Result<(), WriteError> {
+        let Some((offset, mask)) = R::UPDATE_ERROR else {
+            return Ok(());
+        };
+        // The status register is part of the same register block as this register
+        if read_relative(self, offset) & mask == 0 {
+            Ok(())
+        } else {
+            Err(WriteError::UpdateError)
+        }
+    }
+}
+
+/// Register long name of a register that is write protected by a regwen register
+///
+/// Implemented by the `#[registers]` macro for every register with a `regwen` attribute.
+pub trait RegwenProtected: RegisterInfo {
+    /// Offset of the regwen register relative to the protected register & the mask of its
+    /// enable bit
+    const REGWEN: (isize, u32);
+}
+
+/// Register long name of a regwen register that can be cleared by software
+///
+/// Implemented by the `#[registers]` macro for every `rw0c` register used as regwen.
+pub trait RegwenRegister: RegisterInfo {
+    /// Mask of the enable bit
+    const ENABLE: u32;
+}
+
+impl<R: RegwenRegister> ReadWriteZeroToClear<u32, R> {
+    /// Clears the enable bit, all registers protected by this regwen stay locked until reset
+    #[inline]
+    pub fn lock(&self) {
+        self.write_raw(!R::ENABLE);
+    }
+
+    /// Whether the enable bit is cleared
+    #[inline]
+    pub fn is_locked(&self) -> bool {
+        self.get() & R::ENABLE == 0
+    }
+}
+
+/// Writes to registers protected by a regwen register that fail instead of being dropped
+///
+/// Writes through [`Writeable`] to a locked register are silently ignored by the hardware.
+pub trait TryWriteable {
+    type R: RegwenProtected;
+
+    /// Whether writes to the register are ignored since its regwen is cleared
+    fn is_locked(&self) -> bool;
+
+    /// Writes the raw value to the register
+    ///
+    /// Returns `Err(WriteError::Locked)` without writing if the register is locked.
+    fn try_set(&self, value: u32) -> Result<(), WriteError>;
+
+    /// Writes the fields to the register, all other fields are set to 0
+    ///
+    /// Returns `Err(WriteError::Locked)` without writing if the register is locked.
+    fn try_write(&self, field: FieldValue<u32, Self::R>) -> Result<(), WriteError> {
+        self.try_set(field.value)
+    }
+}
+
+impl<W: Writeable<T = u32>> TryWriteable for W
+where
+    W::R: RegwenProtected,
+{
+    type R = W::R;
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        let (offset, mask) = W::R::REGWEN;
+        read_relative(self, offset) & mask == 0
+    }
+
+    #[inline]
+    fn try_set(&self, value: u32) -> Result<(), WriteError> {
+        if self.is_locked() {
+            return Err(WriteError::Locked);
+        }
+        self.set(value);
+        Ok(())
+    }
+}
+
+impl<R: ShadowedRegister + RegwenProtected> TryWriteable for ShadowedReadWrite<u32, R> {
+    type R = R;
+
+    #[inline]
+    fn is_locked(&self) -> bool {
+        let (offset, mask) = R::REGWEN;
+        read_relative(self, offset) & mask == 0
+    }
+
+    #[inline]
+    fn try_set(&self, value: u32) -> Result<(), WriteError> {
+        if self.is_locked() {
+            return Err(WriteError::Locked);
+        }
+        self.set(value)
+    }
+}
+
+/// Error of a register write that did not take effect
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteError {
+    /// The regwen of the register is cleared, the write was not performed
+    Locked,
+    /// The IP reported an update error for the shadowed register
+    UpdateError,
+    /// Status interrupts can't be acknowledged, they stay pending while their cause persists
+    StatusInterrupt,
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteError::Locked => write!(f, "register is locked by its regwen"),
+            WriteError::UpdateError => write!(f, "update error of shadowed register"),
+            WriteError::StatusInterrupt => write!(f, "status interrupts can't be acknowledged"),
+        }
+    }
+}
+
+/// Interrupt registers of an IP
+///
+/// Implemented by the `#[registers]` macro for the register block of every IP with interrupts,
+/// with the generated `Interrupt` enum of the IP.
+pub trait Interrupts {
+    /// Interrupt of the IP, converts to its bit in the interrupt registers
+    type Interrupt: Copy + Into<u32>;
+    /// Register long name of the interrupt registers
+    type Register: RegisterInfo;
+
+    /// Whether `interrupt` is a status interrupt (`type: "status"`)
+    ///
+    /// Status interrupts stay pending while their cause persists, event interrupts stay pending
+    /// until they are acknowledged.
+    fn is_status(interrupt: Self::Interrupt) -> bool;
+
+    fn intr_state(&self) -> &ReadWriteOneToClear<u32, Self::Register>;
+
+    fn intr_enable(&self) -> &ReadWrite<u32, Self::Register>;
+
+    fn intr_test(&self) -> &WriteOnly<u32, Self::Register>;
+
+    /// Enables `interrupt`, pending interrupts that are enabled are signaled to the PLIC
+    fn enable(&self, interrupt: Self::Interrupt) {
+        let enable = self.intr_enable();
+        enable.set(enable.get() | interrupt_mask(interrupt));
+    }
+
+    fn disable(&self, interrupt: Self::Interrupt) {
+        let enable = self.intr_enable();
+        enable.set(enable.get() & !interrupt_mask(interrupt));
+    }
+
+    fn is_enabled(&self, interrupt: Self::Interrupt) -> bool {
+        self.intr_enable().get() & interrupt_mask(interrupt) != 0
+    }
+
+    /// Whether `interrupt` is pending, regardless of whether it is enabled
+    fn is_pending(&self, interrupt: Self::Interrupt) -> bool {
+        self.intr_state().get() & interrupt_mask(interrupt) != 0
+    }
+
+    /// Clears the pending event interrupt `interrupt`
+    ///
+    /// Returns `Err` for status interrupts without writing the register.
+    fn acknowledge(&self, interrupt: Self::Interrupt) -> Result<(), WriteError> {
+        if Self::is_status(interrupt) {
+            return Err(WriteError::StatusInterrupt);
+        }
+        self.intr_state()
+            .clear(Field::new(1, interrupt.into() as usize));
+        Ok(())
+    }
+
+    /// Raises `interrupt` through the interrupt test register, eg. to test interrupt handlers
+    fn force(&self, interrupt: Self::Interrupt) {
+        self.intr_test().set(interrupt_mask(interrupt));
+    }
+}
+
+fn interrupt_mask<I: Into<u32>>(interrupt: I) -> u32 {
+    1 << interrupt.into()
+}
+
+/// Reads the register at `offset` relative to `register`
+///
+/// Both registers have to be part of the same register block.
+#[inline]
+fn read_relative<R>(register: &R, offset: isize) -> u32 {
+    unsafe { read::<u32, ()>((register as *const R as *const u8).offset(offset) as *const u32) }
+}
+
+/// Access policy of a [`Window`]
+pub trait WindowAccess {}
+/// Access policy of a [`Window`] that can be read
+pub trait WindowReadable: WindowAccess {}
+/// Access policy of a [`Window`] that can be written
+pub trait WindowWriteable: WindowAccess {}
+
+/// Window that can only be read (`ro`)
+pub struct ReadOnlyAccess;
+impl WindowAccess for ReadOnlyAccess {}
+impl WindowReadable for ReadOnlyAccess {}
+
+/// Window that can only be written (`wo`)
+pub struct WriteOnlyAccess;
+impl WindowAccess for WriteOnlyAccess {}
+impl WindowWriteable for WriteOnlyAccess {}
+
+/// Window that can be read & written (`rw`)
+pub struct ReadWriteAccess;
+impl WindowAccess for ReadWriteAccess {}
+impl WindowReadable for ReadWriteAccess {}
+impl WindowWriteable for ReadWriteAccess {}
+
+/// Error of an access to a [`Window`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowError {
+    /// The accessed range does not lie inside the window
+    OutOfBounds,
+    /// The window does not support byte writes and the accessed range is not word aligned
+    Unaligned,
+}
+
+impl fmt::Display for WindowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WindowError::OutOfBounds => write!(f, "access outside of the window"),
+            WindowError::Unaligned => write!(f, "unaligned access to a word writeable window"),
+        }
+    }
+}
+
+/// Memory window of `WORDS` words inside a register block (eg. memories or FIFOs)
+///
+/// Every word only holds `VALID_BITS` bits, the others are ignored on writes and read as 0.
+/// Windows with `BYTE_WRITE` accept writes of single bytes, others only of full words.
+#[repr(transparent)]
+pub struct Window<
+    A: WindowAccess,
+    const WORDS: usize,
+    const BYTE_WRITE: bool = false,
+    const VALID_BITS: u32 = 32,
+> {
+    words: [UnsafeCell<u32>; WORDS],
+    access: PhantomData<A>,
+}
+
+impl<A: WindowAccess, const WORDS: usize, const BYTE_WRITE: bool, const VALID_BITS: u32>
+    Window<A, WORDS, BYTE_WRITE, VALID_BITS>
+{
+    const MASK: u32 = if VALID_BITS >= 32 {
+        u32::MAX
+    } else {
+        (1 << VALID_BITS) - 1
+    };
+
+    /// Size of the window in words
+    #[inline]
+    pub const fn words(&self) -> usize {
+        WORDS
+    }
+
+    /// Size of the window in bytes
+    #[inline]
+    pub const fn bytes(&self) -> usize {
+        WORDS * 4
+    }
+
+    /// Checks that `len` elements starting at `offset` fit into a window of `size` elements
+    #[inline]
+    fn check_bounds(offset: usize, len: usize, size: usize) -> Result<(), WindowError> {
+        match offset.checked_add(len) {
+            Some(end) if end <= size => Ok(()),
+            _ => Err(WindowError::OutOfBounds),
+        }
+    }
+}
+
+impl<A: WindowReadable, const WORDS: usize, const BYTE_WRITE: bool, const VALID_BITS: u32>
+    Window<A, WORDS, BYTE_WRITE, VALID_BITS>
+{
+    /// Reads the word at `index`
+    #[inline]
+    pub fn read_word(&self, index: usize) -> Result<u32, WindowError> {
+        let word = self.words.get(index).ok_or(WindowError::OutOfBounds)?;
+        Ok(unsafe { read::<u32, ()>(word.get()) } & Self::MASK)
+    }
+
+    /// Reads `data.len()` words starting at the word `offset` into `data`
+    pub fn copy_to_slice(&self, offset: usize, data: &mut [u32]) -> Result<(), WindowError> {
+        Self::check_bounds(offset, data.len(), WORDS)?;
+        for (word, value) in self.words[offset..].iter().zip(data) {
+            *value = unsafe { read::<u32, ()>(word.get()) } & Self::MASK;
+        }
+        Ok(())
+    }
+
+    /// Reads `data.len()` bytes starting at the byte `offset` into `data`
+    ///
+    /// Words are read as a whole and split into bytes in little endian order.
+    pub fn copy_to_bytes(&self, offset: usize, data: &mut [u8]) -> Result<(), WindowError> {
+        Self::check_bounds(offset, data.len(), WORDS * 4)?;
+        let mut index = offset;
+        let mut data = data.iter_mut().peekable();
+        while data.peek().is_some() {
+            let word = self.read_word(index / 4)?.to_le_bytes();
+            for byte in &word[index % 4..] {
+                let Some(value) = data.next() else { break };
+                *value = *byte;
+                index += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<A: WindowWriteable, const WORDS: usize, const BYTE_WRITE: bool, const VALID_BITS: u32>
+    Window<A, WORDS, BYTE_WRITE, VALID_BITS>
+{
+    /// Writes the word at `index`
+    #[inline]
+    pub fn write_word(&self, index: usize, value: u32) -> Result<(), WindowError> {
+        let word = self.words.get(index).ok_or(WindowError::OutOfBounds)?;
+        unsafe { write::<u32, ()>(word.get(), value & Self::MASK) };
+        Ok(())
+    }
+
+    /// Writes `data` to the words starting at the word `offset`
+    pub fn copy_from_slice(&self, offset: usize, data: &[u32]) -> Result<(), WindowError> {
+        Self::check_bounds(offset, data.len(), WORDS)?;
+        for (word, value) in self.words[offset..].iter().zip(data) {
+            unsafe { write::<u32, ()>(word.get(), value & Self::MASK) };
+        }
+        Ok(())
+    }
+
+    /// Writes `data` to the bytes starting at the byte `offset`
+    ///
+    /// Bytes are combined into words in little endian order. Windows without byte writes only
+    /// accept ranges that cover full words.
+    pub fn copy_from_bytes(&self, offset: usize, data: &[u8]) -> Result<(), WindowError> {
+        Self::check_bounds(offset, data.len(), WORDS * 4)?;
+
+        // Bytes in front of the first full word
+        let head = ((4 - offset % 4) % 4).min(data.len());
+        let (head, rest) = data.split_at(head);
+        let words = rest.chunks_exact(4);
+        let tail = words.remainder();
+        if !BYTE_WRITE && (!head.is_empty() || !tail.is_empty()) {
+            return Err(WindowError::Unaligned);
+        }
+
+        let base = self.words.as_ptr() as *mut u8;
+        for (index, byte) in head.iter().enumerate() {
+            unsafe { write::<u8, ()>(base.add(offset + index), *byte) };
+        }
+        let word_offset = (offset + head.len()) / 4;
+        for (word, value) in self.words[word_offset..].iter().zip(words) {
+            let value = u32::from_le_bytes([value[0], value[1], value[2], value[3]]);
+            unsafe { write::<u32, ()>(word.get(), value & Self::MASK) };
+        }
+        let tail_offset = offset + data.len() - tail.len();
+        for (index, byte) in tail.iter().enumerate() {
+            unsafe { write::<u8, ()>(base.add(tail_offset + index), *byte) };
+        }
+        Ok(())
+    }
+}
+
+/// Register that does not hold its documented reset value
+///
+/// Reported by the `check_reset_state` function generated for every register block.
+#[derive(Debug, Clone, Copy)]
+pub struct ResetMismatch {
+    /// Name of the register in the IP description
+    pub name: &'static str,
+    /// Index of the register for multiregs
+    pub index: Option<usize>,
+    /// Offset of the register inside the register block
+    pub offset: usize,
+    /// Documented reset value
+    pub expected: u32,
+    /// Value that was read
+    pub actual: u32,
+    /// Bits with a known reset value, only those were compared
+    pub mask: u32,
+}
+
+impl fmt::Display for ResetMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(index) = self.index {
+            write!(f, "[{}]", index)?;
+        }
+        write!(
+            f,
+            " @ {:#x}: expected {:#010x}, read {:#010x} (mask {:#010x})",
+            self.offset, self.expected, self.actual, self.mask
+        )
+    }
+}
+
+/// Compares one register of a register block at `base` to its reset value
+///
+/// Used by the generated `check_reset_state` functions, returns whether the register matches.
+#[doc(hidden)]
+pub fn check_reset_value<R: Readable<T = u32>>(
+    register: &R,
+    base: usize,
+    name: &'static str,
+    index: Option<usize>,
+    expected: u32,
+    mask: u32,
+    on_mismatch: &mut impl FnMut(&ResetMismatch),
+) -> bool {
+    let actual = register.get();
+    if (actual ^ expected) & mask == 0 {
+        return true;
+    }
+    on_mismatch(&ResetMismatch {
+        name,
+        index,
+        offset: register as *const R as usize - base,
+        expected,
+        actual,
+        mask,
+    });
+    false
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod host_tests {
+    use super::*;
+
+    tock_registers::register_bitfields![u32,
+        test [
+            a OFFSET(0) NUMBITS(1) [],
+            b OFFSET(4) NUMBITS(2) []
+        ],
+        test_regwen [
+            en OFFSET(0) NUMBITS(1) []
+        ]
+    ];
+
+    #[test]
+    fn clear_policies() {
+        // Registers backed by ram, writes are therefore directly observable
+        let rw1c: ReadWriteOneToClear<u32, test::Register> = ReadWriteOneToClear {
+            value: UnsafeCell::new(0),
+            associated_register: PhantomData,
+        };
+        rw1c.clear(test::b);
+        assert_eq!(rw1c.get(), 0b11_0000);
+
+        let rw0c: ReadWriteZeroToClear<u32, test::Register> = ReadWriteZeroToClear {
+            value: UnsafeCell::new(0),
+            associated_register: PhantomData,
+        };
+        rw0c.clear(test::a);
+        assert_eq!(rw0c.get(), !0b1);
+    }
+
+    #[test]
+    fn reset_value_mask() {
+        let reg: ReadWriteOneToClear<u32> = ReadWriteOneToClear {
+            value: UnsafeCell::new(0b1010),
+            associated_register: PhantomData,
+        };
+        let base = &reg as *const _ as usize;
+        let mut mismatches = 0;
+
+        // Bits outside of the mask are not compared
+        assert!(check_reset_value(
+            &reg,
+            base,
+            "reg",
+            None,
+            0b0010,
+            0b0011,
+            &mut |_| mismatches += 1
+        ));
+        assert!(!check_reset_value(
+            &reg,
+            base,
+            "reg",
+            None,
+            0b0000,
+            0b1111,
+            &mut |m| {
+                assert_eq!(m.actual, 0b1010);
+                mismatches += 1
+            }
+        ));
+        assert_eq!(mismatches, 1);
+    }
+
+    impl RegisterInfo for test::Register {}
+    impl RegisterInfo for test_regwen::Register {}
+
+    impl ShadowedRegister for test::Register {
+        // Status register directly in front of the shadowed register
+        const UPDATE_ERROR: Option<(isize, u32)> = Some((-4, 0b1));
+    }
+
+    #[test]
+    fn shadowed_update_error() {
+        #[repr(C)]
+        struct Block {
+            status: UnsafeCell<u32>,
+            ctrl: ShadowedReadWrite<u32, test::Register>,
+        }
+        let block = Block {
+            status: UnsafeCell::new(0),
+            ctrl: ShadowedReadWrite {
+                value: UnsafeCell::new(0),
+                associated_register: PhantomData,
+            },
+        };
+
+        assert_eq!(block.ctrl.write(test::b.val(0b10)), Ok(()));
+        assert_eq!(block.ctrl.get(), 0b10_0000);
+        unsafe { *block.status.get() = 1 };
+        assert_eq!(
+            block.ctrl.modify(test::a::SET),
+            Err(WriteError::UpdateError)
+        );
+    }
+
+    impl RegwenProtected for test::Register {
+        // Regwen register directly behind the protected register
+        const REGWEN: (isize, u32) = (4, 0b1);
+    }
+
+    impl RegwenRegister for test_regwen::Register {
+        const ENABLE: u32 = 0b1;
+    }
+
+    #[test]
+    fn regwen_lock() {
+        #[repr(C)]
+        struct Block {
+            reg: tock_registers::registers::InMemoryRegister<u32, test::Register>,
+            regwen: ReadWriteZeroToClear<u32, test_regwen::Register>,
+        }
+        let block = Block {
+            reg: tock_registers::registers::InMemoryRegister::new(0),
+            regwen: ReadWriteZeroToClear {
+                value: UnsafeCell::new(1),
+                associated_register: PhantomData,
+            },
+        };
+
+        assert_eq!(block.reg.try_write(test::a::SET), Ok(()));
+        block.regwen.lock();
+        assert!(block.regwen.is_locked());
+        assert_eq!(block.reg.try_set(0), Err(WriteError::Locked));
+    }
+
+    #[test]
+    fn window_copies() {
+        let window: Window<ReadWriteAccess, 2, true> = Window {
+            words: [UnsafeCell::new(0), UnsafeCell::new(0)],
+            access: PhantomData,
+        };
+
+        window.copy_from_bytes(1, &[1, 2, 3, 4, 5]).unwrap();
+        let mut words = [0; 2];
+        window.copy_to_slice(0, &mut words).unwrap();
+        assert_eq!(words, [0x0302_0100, 0x0000_0504]);
+        assert_eq!(
+            window.copy_from_slice(1, &[0, 0]),
+            Err(WindowError::OutOfBounds)
+        );
+    }
+}
@@ -6,6 +6,8 @@
 
 use core::ops::{Deref, DerefMut};
 
+use crate::Error;
+
 pub struct Lock {
     locked: bool,
 }
@@ -15,14 +17,15 @@ impl Lock {
         Lock { locked: false }
     }
 
-    pub fn try_lock(&mut self) -> Result<(), ()> {
+    /// Returns `Err(Error::Busy)` if the lock is held
+    pub fn try_lock(&mut self) -> Result<(), Error> {
         unsafe {
             riscv::interrupt::disable();
             let res = if !self.locked {
                 self.locked = true;
                 Ok(())
             } else {
-                Err(())
+                Err(Error::Busy)
             };
             // TODO: enable once interrupts are supported
             // riscv::interrupt::enable();
@@ -49,14 +52,10 @@ impl<T> Mutex<T> {
         Mutex { lock, elem }
     }
 
-    pub fn try_acquire(&mut self) -> Result<MutexHandle<T>, ()> {
-        unsafe {
-            if (*self.lock).try_lock().is_ok() {
-                Ok(MutexHandle { mutex: self })
-            } else {
-                Err(())
-            }
-        }
+    /// Returns `Err(Error::Busy)` if the ressource is acquired
+    pub fn try_acquire(&mut self) -> Result<MutexHandle<'_, T>, Error> {
+        unsafe { (*self.lock).try_lock()? };
+        Ok(MutexHandle { mutex: self })
     }
 
     /// Releases an acquired ressource
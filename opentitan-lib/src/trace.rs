@@ -0,0 +1,139 @@
+//! Tracing of the register accesses of all IPs (`trace_mmio` feature)
+//!
+//! Every read & write of a register generated by `#[registers]` is printed with the instance &
+//! offset, the name, the value & the fields of the register:
+//!
+//! ```text
+//! [mmio] W uart0+0x10 ctrl = 0x00010003 { tx: 0x1, rx: 0x1, nco: 0x1 }
+//! ```
+//!
+//! Accesses made while printing a trace, eg. to the uart used for output, are not traced.
+
+use core::{
+    fmt,
+    marker::PhantomData,
+    mem, ptr,
+    sync::atomic::{AtomicBool, AtomicPtr, Ordering},
+};
+
+use crate::{devices::addresses, println, registers::RegisterInfo};
+
+/// Set while an access is printed
+static TRACING: AtomicBool = AtomicBool::new(false);
+
+/// The `fn(&str, &str) -> bool` set by `set_filter`, null if all instances are traced
+static FILTER: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+/// Only traces the instances for which `filter` returns true
+///
+/// The filter gets the name & the type of the instance, eg. `("uart1", "uart")`, or `("", "")` for
+/// registers outside of the known address ranges. All instances are traced by default.
+pub fn set_filter(filter: fn(&str, &str) -> bool) {
+    FILTER.store(filter as *mut (), Ordering::Relaxed);
+}
+
+/// Returns whether accesses to the instance `name` of type `ty` are traced
+fn traced(name: &str, ty: &str) -> bool {
+    let filter = FILTER.load(Ordering::Relaxed);
+    if filter.is_null() {
+        return true;
+    }
+    // Only `set_filter` stores to FILTER, the pointer is a `fn(&str, &str) -> bool`
+    let filter = unsafe { mem::transmute::<*mut (), fn(&str, &str) -> bool>(filter) };
+    filter(name, ty)
+}
+
+/// Prints the access of `value` to the register at `addr`
+pub(crate) fn access<T: Copy, R: RegisterInfo>(addr: usize, value: T, write: bool) {
+    // Single hart & no interrupt handlers that access registers, a plain flag is enough
+    if TRACING.load(Ordering::Relaxed) {
+        return;
+    }
+    TRACING.store(true, Ordering::Relaxed);
+
+    let instance = addresses::MODULES
+        .iter()
+        .find(|(_, _, address)| address.contains(addr));
+    let (name, ty) = instance.map_or(("", ""), |(name, ty, _)| (*name, *ty));
+    if traced(name, ty) {
+        let access = Access::<R> {
+            instance: instance.map(|(name, _, address)| (*name, address.base)),
+            addr,
+            value: raw_bits(value),
+            write,
+            register: PhantomData,
+        };
+        // A single print, so the line isn't split by other output
+        println!("{}", access);
+    }
+
+    TRACING.store(false, Ordering::Relaxed);
+}
+
+/// A traced access, formatted as one line of the trace
+struct Access<R> {
+    /// Name & base address of the accessed instance
+    instance: Option<(&'static str, usize)>,
+    addr: usize,
+    value: u64,
+    write: bool,
+    register: PhantomData<R>,
+}
+
+impl<R: RegisterInfo> fmt::Display for Access<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = if self.write { 'W' } else { 'R' };
+        match self.instance {
+            Some((name, base)) => write!(f, "[mmio] {} {}+{:#x}", op, name, self.addr - base)?,
+            None => write!(f, "[mmio] {} {:#x}", op, self.addr)?,
+        }
+        let name = if R::NAME.is_empty() { "?" } else { R::NAME };
+        write!(f, " {} = {:#010x}", name, self.value)?;
+        if !R::FIELDS.is_empty() {
+            write!(f, " {{")?;
+            for (index, (name, offset, width)) in R::FIELDS.iter().enumerate() {
+                let mask = if *width >= 64 {
+                    u64::MAX
+                } else {
+                    (1 << width) - 1
+                };
+                let separator = if index == 0 { "" } else { "," };
+                write!(
+                    f,
+                    "{} {}: {:#x}",
+                    separator,
+                    name,
+                    (self.value >> offset) & mask
+                )?;
+            }
+            write!(f, " }}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the bits of a register value, registers are unsigned integers of up to 64 bits
+fn raw_bits<T: Copy>(value: T) -> u64 {
+    unsafe {
+        match mem::size_of::<T>() {
+            1 => mem::transmute_copy::<T, u8>(&value) as u64,
+            2 => mem::transmute_copy::<T, u16>(&value) as u64,
+            4 => mem::transmute_copy::<T, u32>(&value) as u64,
+            8 => mem::transmute_copy::<T, u64>(&value),
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(all(test, not(target_os = "none")))]
+mod host_tests {
+    use super::*;
+
+    #[test]
+    fn filter_instance() {
+        assert!(traced("uart0", "uart"));
+        set_filter(|name, _| name == "uart1");
+        assert!(traced("uart1", "uart"));
+        assert!(!traced("uart0", "uart"));
+    }
+}
@@ -7,7 +7,7 @@ pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
     let f = parse_macro_input!(input as ItemFn);
 
     // check the function has no arguments
-    if f.sig.inputs.len() != 0 {
+    if !f.sig.inputs.is_empty() {
         return parse::Error::new(
             f.sig.inputs.last().unwrap().span(),
             "`#[entry]` function has too many arguments",
@@ -1,11 +1,7 @@
-use std::{env, path::PathBuf};
-
+use opentitan_codegen::get_opentitan_path;
 use proc_macro::TokenStream;
 
-mod addresses;
 mod entry;
-mod hjson_sanitizer;
-mod registers;
 
 /// Attribute to declare the entry point of the program
 ///
@@ -17,24 +13,42 @@ pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
     entry::entry(args, input)
 }
 
+/// Attribute to generate the register block of an IP from its hjson description
+///
+/// The path is relative to the OpenTitan repository. Descriptions of IPs outside of it, eg. of
+/// an accelerator of a derived top, can be given relative to the crate using the attribute with
+/// `#[registers(crate = "hw/accel.hjson")]` or inline with `#[registers(hjson = r#"{ ... }"#)]`.
+///
+/// Parameters of the IP can be overridden with integer expressions, which may refer to the
+/// defaults of other parameters:
+/// `#[registers("hw/ip/rv_plic/data/rv_plic.hjson", params(NumSrc = 185, NumTargets = 1))]`
+///
+/// The values of the parameters are also emitted as constants of a `params` module next to the
+/// register block, eg. `params::NUM_WORDS` for the `NumWords` parameter. The alerts of the IP are
+/// listed by an `Alert` enum, which the `trigger_test` method of the register block raises. IPs
+/// with interrupts also get an `Interrupt` enum & implement `opentitan_lib::registers::Interrupts`.
 #[proc_macro_attribute]
 pub fn registers(args: TokenStream, item: TokenStream) -> TokenStream {
-    registers::registers(args, item)
+    opentitan_codegen::registers(&get_opentitan_path(), args.into(), item.into())
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
 }
 
+/// Macro to generate the `addresses` module from the hjson description of a top
+///
+/// Every register interface & memory gets an `opentitan_lib::devices::Address` constant, named
+/// after the module & the interface if the module has several, eg. `FLASH_CTRL_CORE`. IPs with
+/// several instances also get an array of them, eg. `UART: [Address; 4]`.
+///
+/// The interrupts & alerts of the top are numbered by the `PlicIrq` & `AlertId` enums, which
+/// are generated next to the module. `AlertId` also knows the alert test register raising the
+/// alert, see `opentitan_lib::devices::alerts`.
+///
+/// Every module also gets a zero sized handle in the `peripherals` module, eg.
+/// `peripherals::Uart0`, which the `Peripherals` struct hands out once for the drivers.
 #[proc_macro]
 pub fn addresses(args: TokenStream) -> TokenStream {
-    addresses::addresses(args)
-}
-
-fn get_opentitan_path() -> PathBuf {
-    if let Ok(ot_path) = env::var("OPENTITAN_PATH") {
-        PathBuf::from(ot_path)
-    } else {
-        let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".into());
-        let mut path = PathBuf::from(manifest_dir);
-        path.pop();
-        path.push("opentitan");
-        path
-    }
+    opentitan_codegen::addresses(&get_opentitan_path(), args.into())
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
 }
@@ -0,0 +1,101 @@
+//! Evaluation of the `#[cfg]` attributes of macro invocations
+//!
+//! The generated code replaces the macros of a build with the `pregenerated` feature for the top
+//! given to the tool, so `feature = "pregenerated"` is false & `opentitan_top = "<name>"` is only
+//! true for that top. All other predicates are taken to be true, the generated code is gated by the
+//! same attributes in the lib.
+
+use syn::{Attribute, Lit, Meta, NestedMeta};
+
+/// Whether all `#[cfg]` attributes of `attrs` are enabled for a pregenerated build for `top`
+pub fn is_enabled(attrs: &[Attribute], top: &str) -> Result<bool, syn::Error> {
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("cfg")) {
+        let Meta::List(list) = attr.parse_meta()? else {
+            return Err(syn::Error::new_spanned(attr, "expected #[cfg(...)]"));
+        };
+        for predicate in &list.nested {
+            if !evaluate(predicate, top)? {
+                return Ok(false);
+            }
+        }
+    }
+    Ok(true)
+}
+
+fn evaluate(predicate: &NestedMeta, top: &str) -> Result<bool, syn::Error> {
+    let NestedMeta::Meta(meta) = predicate else {
+        return Err(syn::Error::new_spanned(predicate, "invalid cfg predicate"));
+    };
+    match meta {
+        Meta::List(list) => {
+            let mut values = list
+                .nested
+                .iter()
+                .map(|predicate| evaluate(predicate, top))
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter();
+            if list.path.is_ident("all") {
+                Ok(values.all(|value| value))
+            } else if list.path.is_ident("any") {
+                Ok(values.any(|value| value))
+            } else if list.path.is_ident("not") && values.len() == 1 {
+                Ok(!values.all(|value| value))
+            } else {
+                Err(syn::Error::new_spanned(list, "invalid cfg predicate"))
+            }
+        }
+        Meta::NameValue(name_value) => {
+            let Lit::Str(value) = &name_value.lit else {
+                return Err(syn::Error::new_spanned(
+                    &name_value.lit,
+                    "expected a string",
+                ));
+            };
+            if name_value.path.is_ident("feature") {
+                Ok(value.value() != "pregenerated")
+            } else if name_value.path.is_ident("opentitan_top") {
+                Ok(value.value() == top)
+            } else {
+                Ok(true)
+            }
+        }
+        Meta::Path(_) => Ok(true),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::{parse_quote, ItemMacro};
+
+    use super::*;
+
+    #[test]
+    fn predicates() {
+        let item: ItemMacro = parse_quote! {
+            #[cfg(all(not(feature = "pregenerated"), opentitan_top = "earlgrey"))]
+            addresses!("hw/top_earlgrey/data/top_earlgrey.hjson");
+        };
+        assert!(is_enabled(&item.attrs, "earlgrey").unwrap());
+        assert!(!is_enabled(&item.attrs, "darjeeling").unwrap());
+
+        let item: ItemMacro = parse_quote! {
+            #[cfg(feature = "pregenerated")]
+            addresses!("hw/top_earlgrey/data/top_earlgrey.hjson");
+        };
+        assert!(!is_enabled(&item.attrs, "earlgrey").unwrap());
+
+        let item: ItemMacro = parse_quote! {
+            #[cfg(any(opentitan_top = "earlgrey", opentitan_top = "darjeeling"))]
+            #[cfg(test)]
+            addresses!("hw/top_earlgrey/data/top_earlgrey.hjson");
+        };
+        assert!(is_enabled(&item.attrs, "darjeeling").unwrap());
+        assert!(!is_enabled(&item.attrs, "englishbreakfast").unwrap());
+
+        let item: ItemMacro = parse_quote! {
+            #[cfg(not(opentitan_top = "earlgrey", test))]
+            addresses!("hw/top_earlgrey/data/top_earlgrey.hjson");
+        };
+        assert!(is_enabled(&item.attrs, "earlgrey").is_err());
+    }
+}
@@ -0,0 +1,250 @@
+//! Formatting of the generated code
+//!
+//! `prettyplease` formats all regular items, but keeps the bodies of the tock-registers macros on
+//! as few lines as possible since it does not know their syntax. These bodies are formatted with
+//! one register, field & field value per line instead.
+
+use proc_macro2::{Delimiter, Group, Spacing, TokenStream, TokenTree};
+use syn::{File, Item, ItemMacro, Lit, Meta};
+
+const INDENT: &str = "    ";
+
+/// Formats the generated items like `rustfmt` would
+pub fn format_items(items: Vec<Item>) -> String {
+    items
+        .into_iter()
+        .map(|item| match item {
+            Item::Macro(item) if is_tock_macro(&item) => format_tock_macro(&item),
+            item => prettyplease::unparse(&File {
+                shebang: None,
+                attrs: vec![],
+                items: vec![item],
+            }),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn is_tock_macro(item: &ItemMacro) -> bool {
+    item.mac.path.segments.last().is_some_and(|segment| {
+        segment.ident == "register_structs" || segment.ident == "register_bitfields"
+    })
+}
+
+fn format_tock_macro(item: &ItemMacro) -> String {
+    let path = item
+        .mac
+        .path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect::<Vec<_>>()
+        .join("::");
+    let (open, close) = match item.mac.delimiter {
+        syn::MacroDelimiter::Paren(_) => ("!(", ");"),
+        syn::MacroDelimiter::Brace(_) => ("! {", "}"),
+        syn::MacroDelimiter::Bracket(_) => ("![", "];"),
+    };
+
+    let mut out = format!("{}{}\n", path, open);
+    format_block(item.mac.tokens.clone(), 1, &mut out);
+    out.push_str(close);
+    out.push('\n');
+    out
+}
+
+/// Formats the comma separated entries of a macro body, each on its own lines
+fn format_block(tokens: TokenStream, indent: usize, out: &mut String) {
+    let tokens = tokens.into_iter().collect::<Vec<_>>();
+    for entry in tokens.split_inclusive(|token| is_punct(token, ',')) {
+        let (mut entry, comma) = match entry.split_last() {
+            Some((last, entry)) if is_punct(last, ',') => (entry, ","),
+            _ => (entry, ""),
+        };
+
+        // Attributes of the entry are placed on their own lines
+        while let [TokenTree::Punct(hash), TokenTree::Group(attr), rest @ ..] = entry {
+            if hash.as_char() != '#' || attr.delimiter() != Delimiter::Bracket {
+                break;
+            }
+            format_attribute(attr, indent, out);
+            entry = rest;
+        }
+
+        out.push_str(&INDENT.repeat(indent));
+        format_inline(entry, indent, out);
+        out.push_str(comma);
+        out.push('\n');
+    }
+}
+
+/// Formats an attribute, doc attributes are written as doc comments
+fn format_attribute(attr: &Group, indent: usize, out: &mut String) {
+    let indent = INDENT.repeat(indent);
+    if let Ok(Meta::NameValue(meta)) = syn::parse2::<Meta>(attr.stream()) {
+        if let (true, Lit::Str(doc)) = (meta.path.is_ident("doc"), &meta.lit) {
+            for line in doc.value().lines() {
+                let separator = if line.is_empty() || line.starts_with(' ') {
+                    ""
+                } else {
+                    " "
+                };
+                out.push_str(&format!("{}///{}{}\n", indent, separator, line));
+            }
+            return;
+        }
+    }
+    out.push_str(&indent);
+    out.push('#');
+    format_inline(&[TokenTree::Group(attr.clone())], 0, out);
+    out.push('\n');
+}
+
+/// Parts of a line of tokens, relevant for the spacing between them
+enum Atom<'a> {
+    Word(String),
+    Punct(String),
+    Group(&'a Group),
+}
+
+/// Formats tokens on a single line, except for nested blocks
+fn format_inline(tokens: &[TokenTree], indent: usize, out: &mut String) {
+    let mut atoms = vec![];
+    let mut iter = tokens.iter().peekable();
+    while let Some(token) = iter.next() {
+        atoms.push(match token {
+            TokenTree::Ident(ident) => Atom::Word(ident.to_string()),
+            TokenTree::Literal(literal) => Atom::Word(literal.to_string()),
+            TokenTree::Group(group) => Atom::Group(group),
+            TokenTree::Punct(punct) => {
+                let mut op = punct.as_char().to_string();
+                // Only paths & the offset arrows are joined, `>` may be followed by `,` or `;`
+                if punct.spacing() == Spacing::Joint {
+                    if let Some(TokenTree::Punct(next)) = iter.peek() {
+                        if matches!((punct.as_char(), next.as_char()), (':', ':') | ('=', '>')) {
+                            op.push(next.as_char());
+                            iter.next();
+                        }
+                    }
+                }
+                Atom::Punct(op)
+            }
+        });
+    }
+
+    let mut prev: Option<&Atom> = None;
+    for atom in &atoms {
+        if prev.is_some_and(|prev| needs_space(prev, atom)) {
+            out.push(' ');
+        }
+        match atom {
+            Atom::Word(word) | Atom::Punct(word) => out.push_str(word),
+            Atom::Group(group) => format_group(group, indent, out),
+        }
+        prev = Some(atom);
+    }
+}
+
+fn format_group(group: &Group, indent: usize, out: &mut String) {
+    let (open, close) = match group.delimiter() {
+        Delimiter::Parenthesis => ('(', ')'),
+        Delimiter::Brace => ('{', '}'),
+        Delimiter::Bracket => ('[', ']'),
+        Delimiter::None => (' ', ' '),
+    };
+    let tokens = group.stream().into_iter().collect::<Vec<_>>();
+    // Register structs & lists with documented entries are split into one entry per line
+    let block = !tokens.is_empty()
+        && match group.delimiter() {
+            Delimiter::Brace => true,
+            Delimiter::Bracket => tokens.iter().any(|token| is_punct(token, '#')),
+            _ => false,
+        };
+
+    out.push(open);
+    if block {
+        out.push('\n');
+        format_block(group.stream(), indent + 1, out);
+        out.push_str(&INDENT.repeat(indent));
+    } else {
+        format_inline(&tokens, indent, out);
+    }
+    out.push(close);
+}
+
+fn needs_space(prev: &Atom, next: &Atom) -> bool {
+    match (prev, next) {
+        (_, Atom::Punct(op)) if matches!(op.as_str(), "," | ";" | ":" | "::" | "<" | ">") => false,
+        (Atom::Punct(op), _) if matches!(op.as_str(), "::" | "<" | "#" | "@" | "&") => false,
+        (Atom::Punct(op), Atom::Group(_)) if op == ">" => false,
+        (Atom::Word(_), Atom::Group(group)) => group.delimiter() != Delimiter::Parenthesis,
+        _ => true,
+    }
+}
+
+fn is_punct(token: &TokenTree, c: char) -> bool {
+    matches!(token, TokenTree::Punct(punct) if punct.as_char() == c)
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::quote;
+
+    use super::*;
+
+    #[test]
+    fn tock_macros() {
+        let file: File = syn::parse2(quote!(
+            tock_registers::register_structs! {
+                pub Regs {
+                    #[doc = "Control\n\nregister"]
+                    (0usize => pub ctrl: [tock_registers::registers::ReadWrite<u32, ctrl::Register>; 2usize]),
+                    (8usize => @END),
+                }
+            }
+            tock_registers::register_bitfields![
+                u32,
+                /// Fields
+                pub ctrl [
+                    #[doc = "Enable"]
+                    en OFFSET(0usize) NUMBITS(1usize) [],
+                    #[doc = "Mode"]
+                    mode OFFSET(1usize) NUMBITS(2usize) [
+                        #[doc = "Off"]
+                        off = 0u32
+                    ]
+                ]
+            ];
+        ))
+        .unwrap();
+
+        assert_eq!(
+            format_items(file.items),
+            "\
+tock_registers::register_structs! {
+    pub Regs {
+        /// Control
+        ///
+        /// register
+        (0usize => pub ctrl: [tock_registers::registers::ReadWrite<u32, ctrl::Register>; 2usize]),
+        (8usize => @END),
+    }
+}
+
+tock_registers::register_bitfields![
+    u32,
+    /// Fields
+    pub ctrl [
+        /// Enable
+        en OFFSET(0usize) NUMBITS(1usize) [],
+        /// Mode
+        mode OFFSET(1usize) NUMBITS(2usize) [
+            /// Off
+            off = 0u32
+        ]
+    ]
+];
+"
+        );
+    }
+}
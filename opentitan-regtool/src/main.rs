@@ -0,0 +1,365 @@
+//! Writes the code of the `#[registers]` & `addresses!` macros of `opentitan-lib` to source files
+//!
+//! With the `pregenerated` feature `opentitan-lib` includes these files instead of invoking the
+//! macros, so it can be built without an OpenTitan checkout.
+//!
+//! Every source file of the lib that uses the macros gets a file with the same path below
+//! `src/generated`, eg. `src/devices/hmac.rs` is generated to `src/generated/devices/hmac.rs`.
+//! The code is generated for one top, selected with `--top <name>` like the `top_<name>` features
+//! of the lib select it. The memory map & the IP types of the top are written to
+//! `src/generated/memory.ld` & `src/generated/ips.txt` for the build script of the lib.
+//! With `--svd <file>` the register map is also exported as a CMSIS-SVD file for debuggers, with
+//! the parameters of the IPs given to the `#[registers]` attributes of the lib.
+//! The tool is a host binary, so the target has to be given explicitly:
+//!
+//! ```text
+//! cargo run -p opentitan-regtool --target x86_64-unknown-linux-gnu -- \
+//!     [--opentitan <path>] [--top <name>] [--check] [--svd <file>] [<opentitan-lib path>]
+//! ```
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+mod cfg;
+mod format;
+
+use opentitan_codegen::get_opentitan_path;
+use proc_macro2::TokenTree;
+use quote::ToTokens;
+use syn::{Item, LitStr};
+
+const USAGE: &str = "\
+Usage: opentitan-regtool [--opentitan <path>] [--top <name>] [--check] [--svd <file>]
+                         [<opentitan-lib path>]
+
+Options:
+    --opentitan <path>  OpenTitan repository, defaults to OPENTITAN_PATH or ../opentitan
+    --top <name>        Top to generate the code for, defaults to earlgrey
+    --check             Only check that the generated files are up to date
+    --svd <file>        Also export the register map of the top as a CMSIS-SVD file
+";
+
+/// Directory below `src` that holds the generated files
+const GENERATED_DIR: &str = "generated";
+
+/// Linker script with the memories of the top, below [`GENERATED_DIR`]
+const MEMORY_MAP: &str = "memory.ld";
+
+/// IP types of the top, one per line, below [`GENERATED_DIR`]
+const IPS: &str = "ips.txt";
+
+fn main() -> ExitCode {
+    let mut opentitan_path = None;
+    let mut lib_path = None;
+    let mut svd_path = None;
+    let mut top_name = "earlgrey".to_owned();
+    let mut check = false;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--opentitan" => match args.next() {
+                Some(path) => opentitan_path = Some(PathBuf::from(path)),
+                None => return usage_error("--opentitan requires a path"),
+            },
+            "--top" => match args.next() {
+                Some(name) => top_name = name,
+                None => return usage_error("--top requires a name"),
+            },
+            "--check" => check = true,
+            "--svd" => match args.next() {
+                Some(path) => svd_path = Some(PathBuf::from(path)),
+                None => return usage_error("--svd requires a path"),
+            },
+            "-h" | "--help" => {
+                print!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            _ if arg.starts_with('-') => return usage_error(&format!("unknown option {}", arg)),
+            _ if lib_path.is_none() => lib_path = Some(PathBuf::from(arg)),
+            _ => return usage_error(&format!("unexpected argument {}", arg)),
+        }
+    }
+    let opentitan_path = opentitan_path.unwrap_or_else(get_opentitan_path);
+    let lib_path = lib_path.unwrap_or_else(|| {
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        manifest_dir.with_file_name("opentitan-lib")
+    });
+
+    // Crate relative IP descriptions are resolved like in a build of the lib
+    env::set_var("CARGO_MANIFEST_DIR", &lib_path);
+
+    let src = lib_path.join("src");
+    let mut sources = vec![];
+    if let Err(error) = collect_sources(&src, &mut sources) {
+        eprintln!("error: can not read {}: {}", src.display(), error);
+        return ExitCode::FAILURE;
+    }
+    sources.sort();
+
+    let mut ok = true;
+    let mut tops = vec![];
+    let mut other_tops = vec![];
+    let mut registers_args = vec![];
+    for source in sources {
+        let relative = source.strip_prefix(&src).unwrap_or(&source);
+        match generate(
+            &opentitan_path,
+            &source,
+            &top_name,
+            &mut tops,
+            &mut other_tops,
+            &mut registers_args,
+        ) {
+            Ok(Some(code)) => ok &= update(&src.join(GENERATED_DIR).join(relative), &code, check),
+            Ok(None) => {}
+            Err(error) => {
+                eprintln!("error: {}", error);
+                ok = false;
+            }
+        }
+    }
+
+    // The build script of the lib uses the memory map & IPs of the top of `addresses!`
+    tops.sort();
+    tops.dedup();
+    match tops.as_slice() {
+        [top] => {
+            match opentitan_codegen::memory_map(&opentitan_path, top) {
+                Ok(memory_map) => {
+                    let code = format!(
+                        "/* Generated by opentitan-regtool from {}, do not edit. */\n\n{}",
+                        top, memory_map
+                    );
+                    ok &= update(&src.join(GENERATED_DIR).join(MEMORY_MAP), &code, check);
+                }
+                Err(error) => {
+                    eprintln!("error: {}", error);
+                    ok = false;
+                }
+            }
+            match opentitan_codegen::ip_types(&opentitan_path, top) {
+                Ok(ips) => {
+                    let code = format!(
+                        "# Generated by opentitan-regtool from {}, do not edit.\n{}\n",
+                        top,
+                        ips.join("\n")
+                    );
+                    ok &= update(&src.join(GENERATED_DIR).join(IPS), &code, check);
+                }
+                Err(error) => {
+                    eprintln!("error: {}", error);
+                    ok = false;
+                }
+            }
+            if let Some(svd_path) = &svd_path {
+                match opentitan_codegen::svd(&opentitan_path, top, &registers_args) {
+                    Ok(svd) => ok &= update(svd_path, &svd, check),
+                    Err(error) => {
+                        eprintln!("error: {}", error);
+                        ok = false;
+                    }
+                }
+            }
+        }
+        [] if !other_tops.is_empty() => {
+            eprintln!(
+                "error: addresses! is not used for the top {}, only for {}",
+                top_name,
+                other_tops.join(", ")
+            );
+            ok = false;
+        }
+        [] if svd_path.is_some() => {
+            eprintln!(
+                "error: addresses! is not used for the top {}, there is no top to export",
+                top_name
+            );
+            ok = false;
+        }
+        [] => {}
+        _ => {
+            eprintln!(
+                "error: addresses! is used with several tops: {}",
+                tops.join(", ")
+            );
+            ok = false;
+        }
+    }
+
+    if ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Writes `code` to `target` if it changed, or only reports that with `check`
+///
+/// Returns whether the file is up to date.
+fn update(target: &Path, code: &str, check: bool) -> bool {
+    let current = fs::read_to_string(target).ok();
+    if current.as_deref() == Some(code) {
+        return true;
+    }
+    if check {
+        eprintln!("error: {} is out of date", target.display());
+        return false;
+    }
+    let written = target
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(target, code));
+    match written {
+        Ok(()) => {
+            println!("generated {}", target.display());
+            true
+        }
+        Err(error) => {
+            eprintln!("error: can not write {}: {}", target.display(), error);
+            false
+        }
+    }
+}
+
+fn usage_error(message: &str) -> ExitCode {
+    eprint!("error: {}\n\n{}", message, USAGE);
+    ExitCode::FAILURE
+}
+
+/// Collects all rust source files below `dir`, except the generated ones
+fn collect_sources(dir: &Path, sources: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if path.file_name().is_some_and(|name| name != GENERATED_DIR) {
+                collect_sources(&path, sources)?;
+            }
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            sources.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Generates the code of all macros used by the source file `path`
+///
+/// Returns `None` if the file does not use any of the macros. Macros that are configured out for
+/// the top `top_name` are skipped. The tops of `addresses!` are added to `tops`, or to `other_tops`
+/// if it is configured out, the arguments of the `#[registers]` attributes to `registers_args`.
+fn generate(
+    opentitan_path: &Path,
+    path: &Path,
+    top_name: &str,
+    tops: &mut Vec<String>,
+    other_tops: &mut Vec<String>,
+    registers_args: &mut Vec<proc_macro2::TokenStream>,
+) -> Result<Option<String>, String> {
+    let source =
+        fs::read_to_string(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+    // Only files that use the macros need to be parsed
+    if !source.contains("registers(") && !source.contains("addresses!") {
+        return Ok(None);
+    }
+    let file = syn::parse_file(&source).map_err(|error| located(path, &error))?;
+
+    let mut hjson_paths = vec![];
+    let mut items = vec![];
+    for item in file.items {
+        match item {
+            Item::Struct(mut item) => {
+                let Some(index) = item
+                    .attrs
+                    .iter()
+                    .position(|attr| is_macro(&attr.path, "registers"))
+                else {
+                    continue;
+                };
+                let attr = item.attrs.remove(index);
+                if !cfg::is_enabled(&item.attrs, top_name).map_err(|error| located(path, &error))? {
+                    continue;
+                }
+                // The macro is only invoked without the `pregenerated` feature
+                item.attrs.retain(|attr| !attr.path.is_ident("cfg"));
+
+                let args = attr
+                    .parse_args::<proc_macro2::TokenStream>()
+                    .map_err(|error| located(path, &error))?;
+                hjson_paths.push(hjson_path(args.clone()));
+                registers_args.push(args.clone());
+                let code =
+                    opentitan_codegen::registers(opentitan_path, args, item.to_token_stream())
+                        .map_err(|error| located(path, &error))?;
+                items.extend(parse_items(path, code)?);
+            }
+            Item::Macro(item) if is_macro(&item.mac.path, "addresses") => {
+                let top = hjson_path(item.mac.tokens.clone());
+                if !cfg::is_enabled(&item.attrs, top_name).map_err(|error| located(path, &error))? {
+                    other_tops.push(top);
+                    continue;
+                }
+                hjson_paths.push(top.clone());
+                tops.push(top);
+                let code = opentitan_codegen::addresses(opentitan_path, item.mac.tokens)
+                    .map_err(|error| located(path, &error))?;
+                items.extend(parse_items(path, code)?);
+            }
+            _ => {}
+        }
+    }
+
+    if items.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(format!(
+        "// Generated by opentitan-regtool from {}, do not edit.\n\n{}",
+        hjson_paths.join(", "),
+        format::format_items(items)
+    )))
+}
+
+/// Whether `path` refers to the macro `name` of `opentitan-macros`
+fn is_macro(path: &syn::Path, name: &str) -> bool {
+    path.segments
+        .last()
+        .is_some_and(|segment| segment.ident == name)
+}
+
+/// Returns the hjson path of the macro arguments `args` for the file header
+fn hjson_path(args: proc_macro2::TokenStream) -> String {
+    let mut tokens = args.into_iter();
+    let token = match tokens.next() {
+        Some(TokenTree::Ident(ident)) if ident == "hjson" => return "<inline>".to_owned(),
+        // The path of a crate relative description follows `crate =`
+        Some(TokenTree::Ident(ident)) if ident == "crate" => tokens.nth(1),
+        token => token,
+    };
+    match token {
+        Some(token) => match syn::parse2::<LitStr>(token.into()) {
+            Ok(path) => path.value(),
+            Err(_) => "<unknown>".to_owned(),
+        },
+        None => "<unknown>".to_owned(),
+    }
+}
+
+fn parse_items(path: &Path, code: proc_macro2::TokenStream) -> Result<Vec<Item>, String> {
+    syn::parse2::<syn::File>(code)
+        .map(|file| file.items)
+        .map_err(|error| located(path, &error))
+}
+
+/// Formats an error at its location in the source file `path`
+fn located(path: &Path, error: &syn::Error) -> String {
+    let start = error.span().start();
+    format!(
+        "{}:{}:{}: {}",
+        path.display(),
+        start.line,
+        start.column + 1,
+        error
+    )
+}